@@ -0,0 +1,35 @@
+use crate::money::Money;
+use crate::records;
+use crate::transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+// Balance of an account before its first entry
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OpeningBalance {
+    pub account: String,
+    pub amount: Money,
+}
+
+fn get_account_filename() -> Result<String, Box<dyn Error>> {
+    Ok(format!("{}/accounts.csv", transaction::get_base_path()?))
+}
+
+pub fn get_opening_balances() -> Result<BTreeMap<String, Money>, Box<dyn Error>> {
+    let opening_balances: Vec<OpeningBalance> = records::read_records(&get_account_filename()?)?;
+    Ok(opening_balances
+        .into_iter()
+        .map(|x| (x.account, x.amount))
+        .collect())
+}
+
+pub fn set_opening_balance(account: &str, amount: Money) -> Result<(), Box<dyn Error>> {
+    let mut opening_balances = get_opening_balances()?;
+    opening_balances.insert(account.to_string(), amount);
+    let opening_balances: Vec<OpeningBalance> = opening_balances
+        .into_iter()
+        .map(|(account, amount)| OpeningBalance { account, amount })
+        .collect();
+    records::write_records(&opening_balances, &get_account_filename()?)
+}
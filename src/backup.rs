@@ -0,0 +1,153 @@
+use crate::config;
+use crate::records;
+use crate::storage;
+use crate::transaction::{self, Transaction};
+use chrono::{Datelike, NaiveDate};
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const BACKUP_DIR: &str = ".backups";
+const DEFAULT_AMOUNT_BACKUPS: usize = 10;
+
+// Number of backups that are kept per month, set by FEONANCIALS_BACKUPS or the config file
+fn get_amount_backups() -> usize {
+    env::var("FEONANCIALS_BACKUPS")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .or(config::get().backups)
+        .unwrap_or(DEFAULT_AMOUNT_BACKUPS)
+}
+
+fn get_month_dir(base_path: &str, year: u32, month: u32) -> String {
+    format!("{}/{}/{}-{:0>2}", base_path, BACKUP_DIR, year, month)
+}
+
+// Copies a month file into the backups before it is overwritten, only the latest ones are kept
+pub fn save(base_path: &str, year: u32, month: u32, filename: &str) -> Result<(), Box<dyn Error>> {
+    if !Path::new(filename).exists() {
+        return Ok(());
+    }
+    let dir = get_month_dir(base_path, year, month);
+    fs::create_dir_all(&dir)?;
+    let stamp = chrono::offset::Local::now().format("%Y%m%d-%H%M%S%3f");
+    let backup = format!("{}/{}.csv", dir, stamp);
+    // when a month is written several times within a millisecond the oldest version is kept
+    if !Path::new(&backup).exists() {
+        fs::copy(filename, backup)?;
+    }
+    let snapshots = get_snapshots(&dir)?;
+    let amount_old = snapshots.len().saturating_sub(get_amount_backups());
+    for snapshot in &snapshots[..amount_old] {
+        fs::remove_file(format!("{}/{}.csv", dir, snapshot))?;
+    }
+    Ok(())
+}
+
+// Names of the backups in a month directory from oldest to newest
+fn get_snapshots(dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|x| x == "csv") {
+            if let Some(stem) = path.file_stem().and_then(|x| x.to_str()) {
+                snapshots.push(stem.to_string());
+            }
+        }
+    }
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+// Prints all backups (of one month "YYYY-MM" if given) as MONTH/SNAPSHOT, which restore takes
+pub fn print_backups(poss_month: &Option<String>) -> Result<(), Box<dyn Error>> {
+    let backup_dir = format!("{}/{}", transaction::get_base_path()?, BACKUP_DIR);
+    if !Path::new(&backup_dir).exists() {
+        println!("No backups");
+        return Ok(());
+    }
+    let mut months: Vec<String> = fs::read_dir(&backup_dir)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|month| poss_month.as_ref().is_none_or(|x| x == month))
+        .collect();
+    months.sort();
+    println!("------------------------------------------------------------");
+    for month in months {
+        let dir = format!("{}/{}", backup_dir, month);
+        for snapshot in get_snapshots(&dir)? {
+            let entries: Vec<Transaction> =
+                records::read_records(&format!("{}/{}.csv", dir, snapshot))?;
+            println!("{}/{}\t{:>4} entries", month, snapshot, entries.len());
+        }
+    }
+    println!("------------------------------------------------------------");
+    Ok(())
+}
+
+// Brings a month back to a backup given as MONTH/SNAPSHOT through the storage, so that the
+// restore is backed up and can be undone like any other change
+pub fn restore(snapshot: &str) -> Result<(), Box<dyn Error>> {
+    let base_path = transaction::get_base_path()?;
+    let error = || format!("Invalid snapshot '{}', expected YYYY-MM/SNAPSHOT", snapshot);
+    let (month, stamp) = snapshot.split_once('/').ok_or_else(error)?;
+    if stamp.is_empty() || !stamp.chars().all(|x| x.is_ascii_digit() || x == '-') {
+        return Err(error().into());
+    }
+    let (year, month) = month.split_once('-').ok_or_else(error)?;
+    let (year, month): (u32, u32) = (year.parse()?, month.parse()?);
+    let backup = format!("{}/{}/{}.csv", base_path, BACKUP_DIR, snapshot);
+    if !Path::new(&backup).exists() {
+        return Err(format!("There is no backup {}", snapshot).into());
+    }
+    // only the csv files are backed up
+    if storage::get_storage_name() != "csv" {
+        return Err("Backups can only be restored into the csv storage".into());
+    }
+    let entries = storage::read_month_file(&backup)?;
+    let mut storage = storage::open()?;
+    let (mut current, others): (Vec<Transaction>, Vec<Transaction>) = storage
+        .load_all()?
+        .into_iter()
+        .partition(|x| (x.date.year() as u32, x.date.month()) == (year, month));
+    // an entry that was moved into another month since would be there twice
+    let others: HashMap<String, NaiveDate> = others.into_iter().map(|x| (x.id, x.date)).collect();
+    if let Some((id, date)) = entries.iter().find_map(|x| others.get_key_value(&x.id)) {
+        return Err(format!(
+            "The entry {} of the backup is on {} now, delete it or move it back first",
+            id, date
+        )
+        .into());
+    }
+    let restored: HashSet<&str> = entries.iter().map(|x| x.id.as_str()).collect();
+    for transaction in current.iter().filter(|x| !restored.contains(x.id.as_str())) {
+        storage.delete(&transaction.id)?;
+    }
+    current.retain(|x| restored.contains(x.id.as_str()));
+    for transaction in &entries {
+        match current.iter().find(|x| x.id == transaction.id) {
+            Some(existing) if get_row(existing)? == get_row(transaction)? => {}
+            Some(_) => storage.update(transaction.clone())?,
+            None => {
+                storage.insert(transaction.clone())?;
+            }
+        }
+    }
+    println!(
+        "Restored {} entries of {}-{:0>2}",
+        entries.len(),
+        year,
+        month
+    );
+    Ok(())
+}
+
+// Row of an entry as it is written into a month file, to tell whether it was changed
+fn get_row(transaction: &Transaction) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    wtr.serialize(transaction)?;
+    Ok(wtr.into_inner()?)
+}
@@ -0,0 +1,96 @@
+use crate::locale;
+use crate::money::Money;
+use crate::records;
+use crate::transaction;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+// Monthly limit for the spending in a category (including its subcategories)
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Budget {
+    pub category: String,
+    pub amount: Money,
+}
+
+// Comparison of a budget with the actual spending in a month
+#[derive(Debug, Clone)]
+pub struct BudgetStatus {
+    pub category: String,
+    pub budget: Money,
+    pub spent: Money,
+}
+
+impl BudgetStatus {
+    pub fn remaining(&self) -> Money {
+        self.budget - self.spent
+    }
+
+    pub fn is_over_budget(&self) -> bool {
+        self.spent > self.budget
+    }
+}
+
+fn get_budget_filename() -> Result<String, Box<dyn Error>> {
+    Ok(format!("{}/budgets.csv", transaction::get_base_path()?))
+}
+
+pub fn get_budgets() -> Result<Vec<Budget>, Box<dyn Error>> {
+    records::read_records(&get_budget_filename()?)
+}
+
+fn write_budgets(budgets: &mut [Budget]) -> Result<(), Box<dyn Error>> {
+    budgets.sort_by(|a, b| a.category.cmp(&b.category));
+    records::write_records(budgets, &get_budget_filename()?)
+}
+
+// Sets the budget of a category, an amount of zero removes it
+pub fn set_budget(category: &str, amount: Money) -> Result<(), Box<dyn Error>> {
+    let mut budgets = get_budgets()?;
+    budgets.retain(|x| x.category != category);
+    if !amount.is_zero() {
+        budgets.push(Budget {
+            category: category.to_string(),
+            amount,
+        });
+    }
+    write_budgets(&mut budgets)
+}
+
+// Compares the budgets with the sums per category, expenses are negative amounts
+pub fn get_budget_status(
+    sums: &BTreeMap<String, Money>,
+) -> Result<Vec<BudgetStatus>, Box<dyn Error>> {
+    Ok(get_budgets()?
+        .into_iter()
+        .map(|budget| BudgetStatus {
+            spent: -sums.get(&budget.category).copied().unwrap_or_default(),
+            category: budget.category,
+            budget: budget.amount,
+        })
+        .collect())
+}
+
+pub fn print_budget_status(status: &[BudgetStatus]) {
+    if status.is_empty() {
+        return;
+    }
+    println!(
+        "{:<30}\t{:>7}\t{:>7}\t{:>9}",
+        "Budget", "Limit", "Spent", "Remaining"
+    );
+    for budget in status {
+        println!(
+            "{:<30}\t{:>7}\t{:>7}\t{:>9}{}",
+            budget.category,
+            locale::format_amount(budget.budget),
+            locale::format_amount(budget.spent),
+            locale::format_amount(budget.remaining()),
+            if budget.is_over_budget() {
+                "\tOVER BUDGET"
+            } else {
+                ""
+            }
+        );
+    }
+}
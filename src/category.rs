@@ -0,0 +1,46 @@
+use crate::locale;
+use crate::money::Money;
+use crate::transaction::Transaction;
+use std::collections::BTreeMap;
+
+// Separates a category from its subcategories, e.g. "Food:Groceries"
+pub const SEPARATOR: char = ':';
+const UNCATEGORIZED: &str = "Uncategorized";
+
+// All categories a category belongs to, from the top level down to itself
+pub fn get_parents(category: &str) -> Vec<String> {
+    if category.is_empty() {
+        return vec![UNCATEGORIZED.to_string()];
+    }
+    category
+        .match_indices(SEPARATOR)
+        .map(|(index, _)| category[..index].to_string())
+        .chain(std::iter::once(category.to_string()))
+        .collect()
+}
+
+// Sums the amounts per category, every entry is also counted for all parent categories
+pub fn get_category_sums<'a>(
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+) -> BTreeMap<String, Money> {
+    let mut sums = BTreeMap::new();
+    for transaction in transactions {
+        for category in get_parents(&transaction.category) {
+            *sums.entry(category).or_default() += transaction.amount;
+        }
+    }
+    sums
+}
+
+// Prints the sums indented by depth, with subcategories below their parents
+pub fn print_category_sums(sums: &BTreeMap<String, Money>) {
+    let mut categories: Vec<(&String, &Money)> = sums.iter().collect();
+    // sorting by the parts keeps "Food:Groceries" next to "Food" even if there is "Food Court"
+    categories.sort_by_key(|(category, _)| category.split(SEPARATOR).collect::<Vec<_>>());
+    for (category, sum) in categories {
+        let depth = category.matches(SEPARATOR).count();
+        let name = category.rsplit(SEPARATOR).next().unwrap_or_default();
+        let label = format!("{}{}", "  ".repeat(depth), name);
+        println!("{:<30}\t{:>7}", label, locale::format_amount(*sum));
+    }
+}
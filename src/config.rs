@@ -0,0 +1,247 @@
+use crate::error::LedgerError;
+use crate::locale::Locale;
+use serde::Deserialize;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+static CONFIG_PATH: OnceLock<String> = OnceLock::new();
+static DATA_DIR: OnceLock<String> = OnceLock::new();
+
+// Settings of config.toml, environment variables and command line flags take precedence
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // directory of the entries, "~/" stands for the home directory
+    pub data_dir: Option<String>,
+    pub currency: Option<String>,
+    pub locale: Option<String>,
+    // chrono format like "%d.%m.%Y" in which dates are shown and typed
+    pub date_format: Option<String>,
+    // account of new entries for which no account is given
+    pub account: Option<String>,
+    // where the entries are kept: "csv", "sqlite" or "log"
+    pub storage: Option<String>,
+    // number of backups that are kept per month
+    pub backups: Option<usize>,
+    pub keys: Keys,
+}
+
+// Keys of the menu, redo is pressed together with Ctrl
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    pub quit: char,
+    pub next_month: char,
+    pub previous_month: char,
+    pub down: char,
+    pub up: char,
+    pub add: char,
+    pub update: char,
+    pub delete: char,
+    pub transfer: char,
+    pub undo: char,
+    pub redo: char,
+    pub reimbursable: char,
+    pub pending: char,
+    pub cash: char,
+}
+
+impl Default for Keys {
+    fn default() -> Keys {
+        Keys {
+            quit: 'q',
+            next_month: 'n',
+            previous_month: 'p',
+            down: 'j',
+            up: 'k',
+            add: 'a',
+            update: 'u',
+            delete: 'd',
+            transfer: 't',
+            undo: 'U',
+            redo: 'r',
+            reimbursable: 'R',
+            pending: 'P',
+            cash: 'C',
+        }
+    }
+}
+
+fn get_home_dir() -> Result<String, Box<dyn Error>> {
+    env::var("HOME").map_err(|_| {
+        LedgerError::MissingConfig(
+            "Cannot find the home directory, set HOME or use --config and --data-dir".to_string(),
+        )
+        .into()
+    })
+}
+
+// XDG directory given by the variable or the fallback in the home directory
+fn get_xdg_dir(variable: &str, fallback: &str) -> Result<String, Box<dyn Error>> {
+    match env::var(variable) {
+        Ok(dir) if !dir.is_empty() => Ok(dir),
+        _ => Ok(format!("{}/{}", get_home_dir()?, fallback)),
+    }
+}
+
+fn get_default_config_path() -> Result<String, Box<dyn Error>> {
+    Ok(format!(
+        "{}/feonancials/config.toml",
+        get_xdg_dir("XDG_CONFIG_HOME", ".config")?
+    ))
+}
+
+// Reads the given config file or config.toml in the config directory if it exists,
+// a data directory given on the command line is used instead of the configured one
+pub fn load(
+    poss_path: &Option<String>,
+    poss_data_dir: &Option<String>,
+    may_be_missing: bool,
+) -> Result<(), Box<dyn Error>> {
+    let path = match poss_path {
+        Some(path) if !may_be_missing && !Path::new(path).exists() => {
+            return Err(LedgerError::MissingConfig(format!(
+                "There is no config file {}, create it or leave out --config",
+                path
+            ))
+            .into())
+        }
+        Some(path) => path.clone(),
+        None => get_default_config_path()?,
+    };
+    let config: Config = if Path::new(&path).exists() {
+        let content = fs::read_to_string(&path)?;
+        let config: Config = toml::from_str(&content).map_err(|e| get_parse_error(&path, e))?;
+        if let Some(Err(message)) = config.locale.as_deref().map(Locale::from_name) {
+            return Err(LedgerError::Parse {
+                file: path.clone(),
+                line: get_line_of_key(&content, "locale"),
+                message,
+            }
+            .into());
+        }
+        config
+    } else {
+        Config::default()
+    };
+    if let Ok(name) = env::var("FEONANCIALS_LOCALE") {
+        Locale::from_name(&name).map_err(|e| format!("{} in FEONANCIALS_LOCALE", e))?;
+    }
+    let _ = CONFIG.set(config);
+    let _ = CONFIG_PATH.set(path);
+    if let Some(data_dir) = poss_data_dir {
+        let _ = DATA_DIR.set(data_dir.clone());
+    }
+    Ok(())
+}
+
+// toml appends the position to the message, the line is kept in the error instead
+fn get_parse_error(path: &str, error: toml::de::Error) -> LedgerError {
+    let message = error.to_string();
+    LedgerError::Parse {
+        file: path.to_string(),
+        line: error
+            .line_col()
+            .map(|(line, _)| line as u64 + 1)
+            .unwrap_or_default(),
+        message: message
+            .split(" at line ")
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+// Line of the setting with the given key, 0 if it cannot be found
+fn get_line_of_key(content: &str, key: &str) -> u64 {
+    content
+        .lines()
+        .position(|line| {
+            line.trim_start()
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+        })
+        .map(|index| index as u64 + 1)
+        .unwrap_or_default()
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+// Directory of the entries from --data-dir, FEONANCIALS_PATH or the config file,
+// the data directory of the user otherwise
+pub fn get_data_dir() -> Result<String, Box<dyn Error>> {
+    if let Some(data_dir) = DATA_DIR.get() {
+        return Ok(data_dir.clone());
+    }
+    if let Ok(data_dir) = env::var("FEONANCIALS_PATH") {
+        return Ok(data_dir);
+    }
+    match &get().data_dir {
+        Some(data_dir) => match data_dir.strip_prefix("~/") {
+            Some(relative) => Ok(format!("{}/{}", get_home_dir()?, relative)),
+            None => Ok(data_dir.clone()),
+        },
+        None => Ok(format!(
+            "{}/feonancials",
+            get_xdg_dir("XDG_DATA_HOME", ".local/share")?
+        )),
+    }
+}
+
+// Creates the data directory when it is used for the first time
+pub fn create_data_dir() -> Result<(), Box<dyn Error>> {
+    let data_dir = get_data_dir()?;
+    if Path::new(&data_dir).exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(&data_dir)?;
+    println!(
+        "Created the data directory {}, set data_dir in {} or use --data-dir to keep \
+         the entries somewhere else",
+        data_dir,
+        get_config_path()?
+    );
+    Ok(())
+}
+
+// Config file that was loaded or would have been loaded if it existed
+pub fn get_config_path() -> Result<String, Box<dyn Error>> {
+    match CONFIG_PATH.get() {
+        Some(path) => Ok(path.clone()),
+        None => get_default_config_path(),
+    }
+}
+
+// Writes a config file that points to the data directory and lists the other settings,
+// an existing config file is left as it is, returns whether the file was written
+pub fn write_config(data_dir: &str) -> Result<bool, Box<dyn Error>> {
+    let path = get_config_path()?;
+    if Path::new(&path).exists() {
+        return Ok(false);
+    }
+    if let Some(dir) = Path::new(&path).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let content = format!(
+        "data_dir = {}\n\
+         # currency = \"EUR\"\n\
+         # locale = \"de\"\n\
+         # date_format = \"%d.%m.%Y\"\n\
+         # account = \"checking\"\n\
+         # storage = \"csv\"\n\
+         # backups = 10\n\
+         \n\
+         # [keys]\n\
+         # add = \"a\"\n\
+         # undo = \"U\"\n",
+        toml::Value::String(data_dir.to_string())
+    );
+    fs::write(path, content)?;
+    Ok(true)
+}
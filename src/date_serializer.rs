@@ -1,7 +1,7 @@
 use chrono::NaiveDate;
 use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 
-fn time_to_csv(t: NaiveDate) -> String {
+pub fn time_to_csv(t: NaiveDate) -> String {
     t.format("%Y-%m-%d").to_string()
 }
 
@@ -0,0 +1,143 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::num::{ParseFloatError, ParseIntError};
+
+// Errors whose kind is told apart by the exit code, so that scripts can react to them
+#[derive(Debug)]
+pub enum LedgerError {
+    // the config file or the directories it is searched in cannot be found
+    MissingConfig(String),
+    // a row of a file cannot be read, lines are counted from 1 including the header
+    Parse {
+        file: String,
+        line: u64,
+        message: String,
+    },
+    InvalidDate {
+        date: String,
+        expected: String,
+    },
+    // the month of the entry has only the given number of entries
+    IndexOutOfRange {
+        index: usize,
+        amount: usize,
+    },
+    // there is no entry with the given id
+    EntryNotFound(String),
+    Io(io::Error),
+    Other(String),
+}
+
+impl LedgerError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LedgerError::Other(_) => 1,
+            LedgerError::MissingConfig(_) => 3,
+            LedgerError::Parse { .. } => 4,
+            LedgerError::InvalidDate { .. } => 5,
+            LedgerError::IndexOutOfRange { .. } | LedgerError::EntryNotFound(_) => 6,
+            LedgerError::Io(_) => 7,
+        }
+    }
+
+    // Error while reading the given csv file
+    pub fn from_csv(file: &str, error: csv::Error) -> LedgerError {
+        if error.is_io_error() {
+            return LedgerError::Io(error.into());
+        }
+        let line = error.position().map(|x| x.line()).unwrap_or_default();
+        let message = match error.kind() {
+            csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+            _ => error.to_string(),
+        };
+        LedgerError::Parse {
+            file: file.to_string(),
+            line,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LedgerError::MissingConfig(message) => write!(f, "{}", message),
+            LedgerError::Parse {
+                file,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", file, line, message),
+            LedgerError::InvalidDate { date, expected } => {
+                write!(f, "Invalid date '{}', expected {}", date, expected)
+            }
+            LedgerError::IndexOutOfRange { index, amount: 0 } => write!(
+                f,
+                "There is no entry with index {}, the month has no entries",
+                index
+            ),
+            LedgerError::IndexOutOfRange { index, amount } => write!(
+                f,
+                "There is no entry with index {}, the indices of the month go from 0 to {} \
+                 as shown by list",
+                index,
+                amount - 1
+            ),
+            LedgerError::EntryNotFound(id) => write!(f, "There is no entry with id {}", id),
+            LedgerError::Io(error) => write!(f, "{}", error),
+            LedgerError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for LedgerError {}
+
+impl From<io::Error> for LedgerError {
+    fn from(error: io::Error) -> LedgerError {
+        LedgerError::Io(error)
+    }
+}
+
+impl From<String> for LedgerError {
+    fn from(message: String) -> LedgerError {
+        LedgerError::Other(message)
+    }
+}
+
+impl From<&str> for LedgerError {
+    fn from(message: &str) -> LedgerError {
+        LedgerError::Other(message.to_string())
+    }
+}
+
+impl From<ParseIntError> for LedgerError {
+    fn from(error: ParseIntError) -> LedgerError {
+        LedgerError::Other(format!("Invalid number: {}", error))
+    }
+}
+
+impl From<ParseFloatError> for LedgerError {
+    fn from(error: ParseFloatError) -> LedgerError {
+        LedgerError::Other(format!("Invalid number: {}", error))
+    }
+}
+
+// Errors of the other modules keep their kind if they are ledger or I/O errors,
+// csv errors of writing or opening a file are I/O errors as well
+impl From<Box<dyn Error>> for LedgerError {
+    fn from(error: Box<dyn Error>) -> LedgerError {
+        let error = match error.downcast::<LedgerError>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<io::Error>() {
+            Ok(error) => return LedgerError::Io(*error),
+            Err(error) => error,
+        };
+        match error.downcast::<csv::Error>() {
+            Ok(error) if error.is_io_error() => LedgerError::Io((*error).into()),
+            Ok(error) => LedgerError::Other(error.to_string()),
+            Err(error) => LedgerError::Other(error.to_string()),
+        }
+    }
+}
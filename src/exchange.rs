@@ -0,0 +1,119 @@
+use crate::config;
+use crate::date_serializer;
+use crate::money::Money;
+use crate::records;
+use crate::transaction::{self, Transaction};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+
+const DEFAULT_CURRENCY: &str = "EUR";
+
+// Value of one unit of a currency in the base currency, valid from the given date
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Rate {
+    #[serde(with = "date_serializer")]
+    pub date: NaiveDate,
+    pub currency: String,
+    pub rate: f64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ExchangeRates {
+    base: String,
+    rates: Vec<Rate>,
+}
+
+pub fn get_base_currency() -> String {
+    env::var("FEONANCIALS_CURRENCY")
+        .ok()
+        .or_else(|| config::get().currency.clone())
+        .unwrap_or_else(|| DEFAULT_CURRENCY.to_string())
+}
+
+fn get_rate_filename() -> Result<String, Box<dyn Error>> {
+    Ok(format!("{}/rates.csv", transaction::get_base_path()?))
+}
+
+impl ExchangeRates {
+    pub fn load() -> Result<ExchangeRates, Box<dyn Error>> {
+        let mut rates: Vec<Rate> = records::read_records(&get_rate_filename()?)?;
+        rates.sort_by_key(|x| x.date);
+        Ok(ExchangeRates {
+            base: get_base_currency(),
+            rates,
+        })
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    // Gets the latest rate of a currency on or before the given date
+    pub fn get_rate(&self, currency: &str, date: NaiveDate) -> Result<f64, Box<dyn Error>> {
+        if currency.is_empty() || currency == self.base {
+            return Ok(1.0);
+        }
+        self.rates
+            .iter()
+            .rev()
+            .find(|x| x.currency == currency && x.date <= date)
+            .map(|x| x.rate)
+            .ok_or_else(|| format!("No exchange rate for {} on {}", currency, date).into())
+    }
+
+    // Gets the amount of a transaction in the base currency, the original amount if there
+    // is no rate for it, which get_missing_rate reports
+    pub fn convert(&self, transaction: &Transaction) -> Money {
+        self.get_rate(&transaction.currency, transaction.date)
+            .map_or(transaction.amount, |rate| transaction.amount.exchange(rate))
+    }
+
+    // Converts the amounts of all transactions into the base currency like convert
+    pub fn to_base(&self, transactions: Vec<Transaction>) -> Vec<Transaction> {
+        transactions
+            .into_iter()
+            .map(|transaction| Transaction {
+                amount: self.convert(&transaction),
+                currency: String::new(),
+                ..transaction
+            })
+            .collect()
+    }
+
+    // Warning about the first of the transactions that has no rate and is not converted
+    pub fn get_missing_rate<'a>(
+        &self,
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+    ) -> Option<String> {
+        transactions.into_iter().find_map(|transaction| {
+            self.get_rate(&transaction.currency, transaction.date)
+                .err()
+                .map(|e| format!("{}, the amount is shown unconverted", e))
+        })
+    }
+}
+
+// Adds the rate of a currency that is valid from the given date on
+pub fn set_rate(date: NaiveDate, currency: &str, rate: f64) -> Result<(), Box<dyn Error>> {
+    let filename = get_rate_filename()?;
+    let mut rates: Vec<Rate> = records::read_records(&filename)?;
+    rates.retain(|x| !(x.date == date && x.currency == currency));
+    rates.push(Rate {
+        date,
+        currency: currency.to_string(),
+        rate,
+    });
+    rates.sort_by(|a, b| (a.date, &a.currency).cmp(&(b.date, &b.currency)));
+    records::write_records(&rates, &filename)
+}
+
+pub fn set_date_rate(
+    poss_date: &Option<String>,
+    currency: &str,
+    rate: f64,
+) -> Result<(), Box<dyn Error>> {
+    let date = transaction::get_date_or_today(poss_date)?;
+    set_rate(date, currency, rate)
+}
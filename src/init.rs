@@ -0,0 +1,84 @@
+use crate::account;
+use crate::config;
+use crate::locale;
+use crate::money::Money;
+use crate::transaction::{self, Transaction};
+use chrono::{Datelike, NaiveDate};
+use std::error::Error;
+use std::fs;
+use std::path;
+
+// Description, category, amount and day of the entries of the sample month
+const SAMPLE_ENTRIES: [(&str, &str, &str, u32); 4] = [
+    ("Salary", "Income", "2500.00", 1),
+    ("Rent", "Housing", "-900.00", 1),
+    ("Supermarket", "Food:Groceries", "-54.20", 3),
+    ("Coffee", "Food:Eating out", "-3.50", 5),
+];
+
+// Creates the data directory and a config file that points to it, optionally with the
+// opening balance of an account and sample entries in the current month
+pub fn init(
+    opening_balance: Option<Money>,
+    poss_account: &Option<String>,
+    sample: bool,
+) -> Result<(), Box<dyn Error>> {
+    let data_dir = path::absolute(transaction::get_base_path()?)?
+        .to_string_lossy()
+        .to_string();
+    if !transaction::get_months()?.is_empty() {
+        return Err(format!("There is already a ledger in {}", data_dir).into());
+    }
+    fs::create_dir_all(&data_dir)?;
+    println!("Created the ledger in {}", data_dir);
+
+    let config_path = config::get_config_path()?;
+    if config::write_config(&data_dir)? {
+        println!("Wrote the config file {}", config_path);
+    } else if config::get().data_dir.as_deref() == Some(&data_dir) {
+        println!("Left the existing config file {} unchanged", config_path);
+    } else {
+        println!(
+            "Left the existing config file {} unchanged, use --data-dir {} for this ledger",
+            config_path, data_dir
+        );
+    }
+
+    let account = poss_account
+        .clone()
+        .or_else(|| config::get().account.clone())
+        .unwrap_or_default();
+    if let Some(amount) = opening_balance {
+        // opening balances are stored by the name the account is shown with
+        let name = match account.is_empty() {
+            true => transaction::DEFAULT_ACCOUNT,
+            false => &account,
+        };
+        account::set_opening_balance(name, amount)?;
+        println!(
+            "Set the opening balance of {} to {}",
+            name,
+            locale::format_amount(amount)
+        );
+    }
+
+    if sample {
+        let today = chrono::offset::Local::today().naive_local();
+        for (description, category, amount, day) in SAMPLE_ENTRIES {
+            transaction::add_transaction(Transaction {
+                date: NaiveDate::from_ymd(today.year(), today.month(), day),
+                amount: amount.parse()?,
+                description: description.to_string(),
+                category: category.to_string(),
+                account: account.clone(),
+                ..Transaction::default()
+            })?;
+        }
+        println!(
+            "Added {} sample entries to {}",
+            SAMPLE_ENTRIES.len(),
+            today.format("%Y-%m")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use crate::config;
+use crate::money::Money;
+use std::env;
+
+// How amounts are written, the files always use the plain "1234.50" format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub decimal: char,
+    pub thousands: Option<char>,
+    pub symbol_before: bool,
+}
+
+impl Default for Locale {
+    fn default() -> Locale {
+        Locale {
+            decimal: '.',
+            thousands: None,
+            symbol_before: false,
+        }
+    }
+}
+
+impl Locale {
+    // Known locales by language or country, e.g. "de", "de_DE.UTF-8" or "en_US"
+    pub fn from_name(name: &str) -> Result<Locale, String> {
+        let name = name.split('.').next().unwrap_or_default().to_lowercase();
+        let (language, country) = name.split_once(['_', '-']).unwrap_or((&name, ""));
+        let locale = |decimal, thousands, symbol_before| Locale {
+            decimal,
+            thousands: Some(thousands),
+            symbol_before,
+        };
+        match (language, country) {
+            ("", _) | ("c", _) | ("posix", _) => Ok(Locale::default()),
+            (_, "ch") => Ok(locale('.', '\'', true)),
+            ("de", _) | ("es", _) | ("it", _) | ("nl", _) | ("pt", _) => {
+                Ok(locale(',', '.', false))
+            }
+            ("fr", _) | ("pl", _) | ("sv", _) | ("fi", _) | ("cs", _) => {
+                Ok(locale(',', ' ', false))
+            }
+            ("en", _) | ("ja", _) | ("zh", _) => Ok(locale('.', ',', true)),
+            _ => Err(format!("Unknown locale '{}'", name)),
+        }
+    }
+
+    // Locale set by FEONANCIALS_LOCALE or the config file, whose names are checked when
+    // the config is loaded
+    pub fn current() -> Locale {
+        env::var("FEONANCIALS_LOCALE")
+            .ok()
+            .or_else(|| config::get().locale.clone())
+            .and_then(|name| Locale::from_name(&name).ok())
+            .unwrap_or_default()
+    }
+
+    // Formats an amount with two decimal places and grouped thousands
+    pub fn format(&self, amount: Money) -> String {
+        let plain = amount.to_string();
+        let (sign, digits) = match plain.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", &plain[..]),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, "00"));
+        let mut grouped = String::new();
+        for (index, digit) in integer.chars().enumerate() {
+            if index > 0 && (integer.len() - index) % 3 == 0 {
+                if let Some(thousands) = self.thousands {
+                    grouped.push(thousands);
+                }
+            }
+            grouped.push(digit);
+        }
+        format!("{}{}{}{}", sign, grouped, self.decimal, fraction)
+    }
+
+    // Formats an amount together with the symbol of its currency
+    pub fn format_currency(&self, amount: Money, currency: &str) -> String {
+        let symbol = get_symbol(currency);
+        if self.symbol_before {
+            let formatted = self.format(amount);
+            match formatted.strip_prefix('-') {
+                Some(formatted) => format!("-{}{}", symbol, formatted),
+                None => format!("{}{}", symbol, formatted),
+            }
+        } else {
+            format!("{} {}", self.format(amount), symbol)
+        }
+    }
+
+    // Parses amounts like "1.234,50" or "-12,5", a currency symbol is ignored.
+    // Thousands separators are only accepted between groups of three digits, so
+    // that "12.50" is rejected instead of read as 1250 when '.' groups thousands
+    pub fn parse(&self, s: &str) -> Result<Money, String> {
+        let error = || format!("Invalid amount '{}'", s);
+        self.get_canonical(s)
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(error)
+    }
+
+    // Parses numbers with more decimal places than amounts like exchange rates
+    pub fn parse_number(&self, s: &str) -> Result<f64, String> {
+        let error = || format!("Invalid number '{}'", s);
+        self.get_canonical(s)
+            .and_then(|x| x.parse().ok())
+            .ok_or_else(error)
+    }
+
+    // Number written with '.' as decimal separator and without thousands separators
+    fn get_canonical(&self, s: &str) -> Option<String> {
+        let trimmed: String = s.trim().chars().filter(|c| !"€$£¥".contains(*c)).collect();
+        let trimmed = trimmed.trim();
+        let (sign, digits) = match trimmed.chars().next() {
+            Some(c @ ('-' | '+')) => (c.to_string(), trimmed[1..].trim_start()),
+            _ => (String::new(), trimmed),
+        };
+        let (integer, fraction) = match digits.split_once(self.decimal) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (digits, None),
+        };
+        let integer = match self.thousands {
+            Some(thousands) => {
+                let separators: &[char] = if thousands == ' ' {
+                    &[' ', '\u{a0}']
+                } else {
+                    &[thousands]
+                };
+                let groups: Vec<&str> = integer.split(separators).collect();
+                if groups.len() > 1
+                    && (groups[0].is_empty()
+                        || groups[0].len() > 3
+                        || groups[1..].iter().any(|x| x.len() != 3))
+                {
+                    return None;
+                }
+                groups.concat()
+            }
+            None => integer.to_string(),
+        };
+        Some(match fraction {
+            Some(fraction) => format!("{}{}.{}", sign, integer, fraction),
+            None => format!("{}{}", sign, integer),
+        })
+    }
+}
+
+// Symbol of a currency code, the code itself for currencies without a common symbol
+pub fn get_symbol(currency: &str) -> &str {
+    match currency {
+        "EUR" => "€",
+        "USD" => "$",
+        "GBP" => "£",
+        "JPY" | "CNY" => "¥",
+        _ => currency,
+    }
+}
+
+// Formats an amount in the current locale
+pub fn format_amount(amount: Money) -> String {
+    Locale::current().format(amount)
+}
+
+// Parses an amount in the current locale
+pub fn parse_amount(s: &str) -> Result<Money, String> {
+    Locale::current().parse(s)
+}
+
+// Parses a number like an exchange rate in the current locale
+pub fn parse_number(s: &str) -> Result<f64, String> {
+    Locale::current().parse_number(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn finds_locales_by_language_or_country() {
+        let german = Locale::from_name("de_DE.UTF-8").unwrap();
+        assert_eq!((german.decimal, german.thousands), (',', Some('.')));
+        assert_eq!(Locale::from_name("de-CH").unwrap().thousands, Some('\''));
+        assert_eq!(Locale::from_name("C").unwrap(), Locale::default());
+        assert!(Locale::from_name("ru").is_err());
+    }
+
+    #[test]
+    fn parses_amounts_in_german() {
+        let german = Locale::from_name("de").unwrap();
+        assert_eq!(german.parse("1.234,50"), Ok(money("1234.50")));
+        assert_eq!(german.parse("-12,5"), Ok(money("-12.50")));
+        assert_eq!(german.parse("12,50 €"), Ok(money("12.50")));
+        assert_eq!(german.parse("1.234.567"), Ok(money("1234567")));
+    }
+
+    #[test]
+    fn parses_rates_with_more_decimal_places() {
+        let german = Locale::from_name("de").unwrap();
+        assert_eq!(german.parse_number("0,9234"), Ok(0.9234));
+        assert_eq!(german.parse_number("1.234,5"), Ok(1234.5));
+        assert!(german.parse_number("0.92").is_err());
+    }
+
+    #[test]
+    fn rejects_misplaced_thousands_separators() {
+        let german = Locale::from_name("de").unwrap();
+        for invalid in ["12.50", "1.23,4", ".123", "1234.567", "12,5,0"] {
+            assert!(german.parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn parses_spaces_as_thousands_separators_in_french() {
+        let french = Locale::from_name("fr").unwrap();
+        assert_eq!(french.parse("1 234,50"), Ok(money("1234.50")));
+        assert_eq!(french.parse("1\u{a0}234,50"), Ok(money("1234.50")));
+    }
+
+    #[test]
+    fn formats_amounts_and_currencies() {
+        let german = Locale::from_name("de").unwrap();
+        assert_eq!(german.format(money("-1234567.5")), "-1.234.567,50");
+        assert_eq!(german.format_currency(money("12"), "EUR"), "12,00 €");
+        let english = Locale::from_name("en").unwrap();
+        assert_eq!(english.format_currency(money("-1234"), "USD"), "-$1,234.00");
+        assert_eq!(Locale::default().format(money("1234")), "1234.00");
+    }
+}
@@ -1,29 +1,91 @@
 use clap::{Parser, Subcommand};
+use error::LedgerError;
+use transaction::{Filter, Transaction};
 
+mod account;
+mod backup;
+mod budget;
+mod category;
+mod config;
 mod date_serializer;
+mod error;
+mod exchange;
+mod init;
+mod locale;
+mod money;
+mod records;
+mod recurrence;
+mod set_serializer;
+mod storage;
 mod transaction;
 mod tui;
 
+// Exit codes of LedgerError, clap exits with 2 on invalid arguments
+const EXIT_CODES: &str = "EXIT CODES:
+    1    Other errors
+    2    Invalid arguments
+    3    Missing config file or home directory
+    4    Malformed row in a data or config file
+    5    Invalid date
+    6    Entry not found (unknown id or index out of range)
+    7    I/O error";
+
 #[derive(Parser)]
+#[clap(after_help = EXIT_CODES)]
 struct Arguments {
     #[clap(subcommand)]
     command: Option<Commands>,
+
+    /// Config file to use instead of feonancials/config.toml in the config directory
+    #[clap(long, global = true, action)]
+    config: Option<String>,
+
+    /// Directory of the entries, overrides FEONANCIALS_PATH and the config file
+    #[clap(long, global = true, action)]
+    data_dir: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    #[clap(allow_negative_numbers = true)]
     Add {
         #[clap(long, short, action)]
         date: Option<String>,
 
+        /// Amount of an expense, income is marked with a leading '+' or --income
         #[clap(value_parser)]
-        amount: f64,
+        amount: String,
 
         #[clap(value_parser)]
         description: String,
 
+        /// Repeat interval like 1d, 2w, 1m or 1y, optionally followed by
+        /// ";until=DATE", ";times=N", ";skip=DATE" or ";change=DATE:AMOUNT",
+        /// quoted as ';' ends a command in the shell
         #[clap(value_parser)]
         repeat: Option<String>,
+
+        /// Category with subcategories separated by ':', e.g. Food:Groceries
+        #[clap(long, short, action)]
+        category: Option<String>,
+
+        #[clap(long = "tag", short, action)]
+        tags: Vec<String>,
+
+        /// Markers like reimbursable, pending or cash
+        #[clap(long = "switch", short, action)]
+        switches: Vec<String>,
+
+        #[clap(long, short, action)]
+        account: Option<String>,
+
+        /// Currency code of the amount if it is not the base currency
+        #[clap(long, action)]
+        currency: Option<String>,
+
+        /// Adds the amount as income instead of an expense
+        #[clap(long, short, action)]
+        income: bool,
     },
 
     List {
@@ -32,38 +94,435 @@ enum Commands {
 
         #[clap(long, short, action)]
         full: bool,
+
+        /// Only list entries that have all of the given tags
+        #[clap(long = "tag", short, action)]
+        tags: Vec<String>,
+
+        /// Only list entries that have all of the given switches
+        #[clap(long = "switch", short, action)]
+        switches: Vec<String>,
+
+        /// Only list entries of the given account
+        #[clap(long, short, action)]
+        account: Option<String>,
     },
 
     Del {
         #[clap(long, short, action)]
         date: Option<String>,
 
+        /// Only delete the entry if it belongs to the given account
+        #[clap(long, short, action)]
+        account: Option<String>,
+
+        /// Id of the entry or its index in the month as shown by list
+        #[clap(value_parser)]
+        entry: String,
+    },
+
+    /// Changes the given fields of an entry, the other fields are kept
+    Edit {
+        /// Month of the entry if it is given by its index (YYYY-MM), the current month if not given
+        #[clap(long, short, action)]
+        month: Option<String>,
+
+        /// Id of the entry or its index in the month as shown by list
+        #[clap(value_parser)]
+        entry: String,
+
+        #[clap(long, short, action)]
+        date: Option<String>,
+
+        /// Amount of an expense, income is marked with a leading '+'
+        #[clap(long, action, allow_hyphen_values = true)]
+        amount: Option<String>,
+
+        #[clap(long, action)]
+        description: Option<String>,
+
+        /// Repeat interval with the same syntax as in add, an empty value stops repeating
+        #[clap(long, short, action)]
+        repeat: Option<String>,
+
+        #[clap(long, short, action)]
+        category: Option<String>,
+
+        #[clap(long, short, action)]
+        account: Option<String>,
+    },
+
+    /// Moves money from one account to another
+    Transfer {
+        #[clap(long, short, action)]
+        date: Option<String>,
+
+        #[clap(long, short, action)]
+        from: String,
+
+        #[clap(long, short, action)]
+        to: String,
+
+        #[clap(value_parser)]
+        amount: String,
+
+        #[clap(value_parser)]
+        description: Option<String>,
+    },
+
+    Generate {
+        /// Last month to generate entries for (YYYY-MM)
+        #[clap(long, short, action)]
+        until: String,
+    },
+
+    Report {
+        #[clap(subcommand)]
+        report: Reports,
+    },
+
+    Budget {
+        #[clap(subcommand)]
+        budget: Budgets,
+    },
+
+    /// Shows the balance of every account
+    Balance {
+        /// Month at whose end the balances are shown (YYYY-MM), the current month if not given
+        #[clap(long, short, action)]
+        month: Option<String>,
+    },
+
+    /// Starts a new ledger with a config file that points to it
+    Init {
+        /// Directory of the new ledger, the configured data directory if not given
+        #[clap(value_parser)]
+        path: Option<String>,
+
+        /// Balance of the account before its first entry
+        #[clap(long, value_parser, allow_hyphen_values = true)]
+        opening_balance: Option<String>,
+
+        /// Account of the opening balance and the sample entries
+        #[clap(long, short, action)]
+        account: Option<String>,
+
+        /// Adds a few sample entries to the current month
+        #[clap(long, action)]
+        sample: bool,
+    },
+
+    /// Sets the balance of an account before its first entry
+    OpeningBalance {
+        #[clap(value_parser)]
+        account: String,
+
+        #[clap(value_parser, allow_hyphen_values = true)]
+        amount: String,
+    },
+
+    /// Sets the exchange rate of a currency into the base currency
+    Rate {
+        /// Date from which on the rate is used, today if not given
+        #[clap(long, short, action)]
+        date: Option<String>,
+
         #[clap(value_parser)]
-        index: usize,
+        currency: String,
+
+        /// Value of one unit of the currency in the base currency
+        #[clap(value_parser)]
+        rate: String,
     },
 
+    /// Flips the sign of entries that were entered with the wrong sign
+    FlipSigns {
+        /// First month whose entries are flipped (YYYY-MM)
+        #[clap(long, short, action)]
+        from: String,
+
+        /// Last month whose entries are flipped (YYYY-MM), only the first month if not given
+        #[clap(long, short, action)]
+        until: Option<String>,
+
+        /// Only flips positive amounts, e.g. expenses that were added in the menu as typed
+        #[clap(long, short, action)]
+        positive: bool,
+
+        /// Only flips entries of the given account
+        #[clap(long, short, action)]
+        account: Option<String>,
+
+        /// Only shows the entries that would be flipped
+        #[clap(long, action)]
+        dry_run: bool,
+    },
+
+    /// Copies all entries into another storage, set storage in the config file or
+    /// FEONANCIALS_STORAGE to use it
+    CopyStorage {
+        /// Storage to copy the entries into, csv, sqlite or log
+        #[clap(value_parser)]
+        to: String,
+
+        /// Storage to copy the entries from, the selected one if not given
+        #[clap(long, short, action)]
+        from: Option<String>,
+    },
+
+    /// Writes the entries of the log storage into a snapshot and empties the log,
+    /// which drops the history of the entries. When the log is merged with another
+    /// computer, merge in both directions first and then compact on both computers
+    CompactLog,
+
+    /// Adds the events of another log to the log storage, e.g. of another computer,
+    /// both data directories need the same snapshot, see compact-log
+    MergeLog {
+        /// Log file of the other data directory (feonancials.log)
+        #[clap(value_parser)]
+        file: String,
+    },
+
+    Backup {
+        #[clap(subcommand)]
+        backup: Backups,
+    },
+
+    /// Replaces a month with one of its backups, which can be undone like any other change
+    Restore {
+        /// Backup as shown by backup list (YYYY-MM/SNAPSHOT)
+        #[clap(value_parser)]
+        snapshot: String,
+    },
+
+    /// Reverts the last change to the entries of the selected storage
+    Undo,
+
+    /// Makes the last undone change again
+    Redo,
+
     Menu,
 }
 
+#[derive(Subcommand)]
+enum Backups {
+    /// Shows the backups that were made before month files were written
+    List {
+        /// Only shows the backups of this month (YYYY-MM)
+        #[clap(long, short, action)]
+        month: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Budgets {
+    /// Sets the monthly budget of a category, 0 removes it
+    Set {
+        #[clap(value_parser)]
+        category: String,
+
+        #[clap(value_parser)]
+        amount: String,
+    },
+
+    /// Shows budget, spending and remaining amount per category
+    Show {
+        /// Month to compare with (YYYY-MM), the current month if not given
+        #[clap(long, short, action)]
+        month: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Reports {
+    /// Entries of all months that are still marked as reimbursable
+    Reimbursable,
+
+    /// Sums per category and subcategory of a month
+    Categories {
+        /// Month of the report (YYYY-MM), the current month if not given
+        #[clap(long, short, action)]
+        month: Option<String>,
+    },
+}
+
 fn main() {
     let arg = Arguments::parse();
     let command = &arg.command;
     if command.is_none() {
     } else {
-        let res = match &arg.command.unwrap() {
-            Commands::Add {
-                date,
-                amount,
-                description,
-                repeat,
-            } => transaction::add_date_entry(date, *amount, description, repeat),
-            Commands::List { date, full } => transaction::print_date_list(date, *full),
-            Commands::Del { date, index } => transaction::del_entry(date, *index),
-            Commands::Menu => tui::show_tui(),
+        let command = arg.command.unwrap();
+        // init creates the directory and the config file itself
+        let loaded = match &command {
+            Commands::Init { path, .. } => {
+                config::load(&arg.config, &path.clone().or(arg.data_dir), true)
+            }
+            _ => config::load(&arg.config, &arg.data_dir, false)
+                .and_then(|_| config::create_data_dir()),
         };
-        if let Err(r) = res {
+        if let Err(r) = loaded {
+            let r = LedgerError::from(r);
+            eprintln!("{}", r);
+            std::process::exit(r.exit_code())
+        }
+        if let Err(r) = run(&command) {
             eprintln!("{}", r);
-            std::process::exit(1)
+            std::process::exit(r.exit_code())
+        }
+    }
+}
+
+fn run(command: &Commands) -> Result<(), LedgerError> {
+    match command {
+        Commands::Add {
+            date,
+            amount,
+            description,
+            repeat,
+            category,
+            tags,
+            switches,
+            account,
+            currency,
+            income,
+        } => transaction::add_date_entry(
+            date,
+            amount,
+            *income,
+            description,
+            repeat,
+            Transaction {
+                category: category.clone().unwrap_or_default(),
+                account: account
+                    .clone()
+                    .or_else(|| config::get().account.clone())
+                    .unwrap_or_default(),
+                currency: currency.clone().unwrap_or_default(),
+                tags: tags.iter().cloned().collect(),
+                switches: switches.iter().cloned().collect(),
+                ..Transaction::default()
+            },
+        )?,
+        Commands::List {
+            date,
+            full,
+            tags,
+            switches,
+            account,
+        } => transaction::print_date_list(
+            date,
+            *full,
+            &Filter {
+                account: account.clone(),
+                tags: tags.clone(),
+                switches: switches.clone(),
+            },
+        )?,
+        Commands::Del {
+            date,
+            account,
+            entry,
+        } => transaction::del_entry(
+            date,
+            entry,
+            &Filter {
+                account: account.clone(),
+                ..Filter::default()
+            },
+        )?,
+        Commands::Edit {
+            month,
+            entry,
+            date,
+            amount,
+            description,
+            repeat,
+            category,
+            account,
+        } => transaction::edit_entry(
+            month,
+            entry,
+            &transaction::Changes {
+                date: date.clone(),
+                amount: amount.clone(),
+                description: description.clone(),
+                repeat: repeat.clone(),
+                category: category.clone(),
+                account: account.clone(),
+            },
+        )?,
+        Commands::Transfer {
+            date,
+            from,
+            to,
+            amount,
+            description,
+        } => transaction::add_date_transfer(
+            date,
+            locale::parse_amount(amount)?,
+            from,
+            to,
+            description,
+        )?,
+        Commands::Generate { until } => transaction::generate_entries(until)?,
+        Commands::Report { report } => match report {
+            Reports::Reimbursable => transaction::print_reimbursable()?,
+            Reports::Categories { month } => transaction::print_category_report(month)?,
+        },
+        Commands::Budget { budget } => match budget {
+            Budgets::Set { category, amount } => {
+                budget::set_budget(category, locale::parse_amount(amount)?)?
+            }
+            Budgets::Show { month } => transaction::print_budgets(month)?,
+        },
+        Commands::Balance { month } => transaction::print_balances(month)?,
+        Commands::Init {
+            opening_balance,
+            account,
+            sample,
+            ..
+        } => init::init(
+            opening_balance
+                .as_deref()
+                .map(locale::parse_amount)
+                .transpose()?,
+            account,
+            *sample,
+        )?,
+        Commands::OpeningBalance { account, amount } => {
+            account::set_opening_balance(account, locale::parse_amount(amount)?)?
         }
+        Commands::Rate {
+            date,
+            currency,
+            rate,
+        } => exchange::set_date_rate(date, currency, locale::parse_number(rate)?)?,
+        Commands::FlipSigns {
+            from,
+            until,
+            positive,
+            account,
+            dry_run,
+        } => transaction::flip_signs(
+            from,
+            until,
+            *positive,
+            &Filter {
+                account: account.clone(),
+                ..Filter::default()
+            },
+            *dry_run,
+        )?,
+        Commands::CopyStorage { to, from } => storage::copy_storage(to, from)?,
+        Commands::CompactLog => storage::compact_log()?,
+        Commands::MergeLog { file } => storage::merge_log(file)?,
+        Commands::Backup { backup } => match backup {
+            Backups::List { month } => backup::print_backups(month)?,
+        },
+        Commands::Restore { snapshot } => backup::restore(snapshot)?,
+        Commands::Undo => println!("{}", transaction::undo()?),
+        Commands::Redo => println!("{}", transaction::redo()?),
+        Commands::Menu => tui::show_tui()?,
     }
+    Ok(())
 }
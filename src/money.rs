@@ -0,0 +1,174 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+// Amount of money in cents, so that sums do not pick up floating point errors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+impl Money {
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn abs(&self) -> Money {
+        Money(self.0.abs())
+    }
+
+    // Converts into another currency, rounded to whole cents
+    pub fn exchange(&self, rate: f64) -> Money {
+        Money((self.0 as f64 * rate).round() as i64)
+    }
+}
+
+// Always shows two decimal places, width and alignment of the formatter are respected
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cents = self.0.unsigned_abs();
+        let digits = format!("{}.{:0>2}", cents / 100, cents % 100);
+        f.pad_integral(self.0 >= 0, "", &digits)
+    }
+}
+
+// Parses amounts like "12", "-12.5" or "12.30", more than two decimal places are
+// rounded, since older files contain amounts like "12.300000000000001"
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Money, String> {
+        let error = || format!("Invalid amount '{}'", s);
+        let trimmed = s.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if (integer.is_empty() && fraction.is_empty())
+            || !integer.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(error());
+        }
+        let integer: i64 = if integer.is_empty() {
+            0
+        } else {
+            integer.parse().map_err(|_| error())?
+        };
+        let mut fraction_digits = fraction.bytes().map(|c| (c - b'0') as i64);
+        let tenths = fraction_digits.next().unwrap_or(0);
+        let hundredths = fraction_digits.next().unwrap_or(0);
+        let round_up = fraction_digits.next().unwrap_or(0) >= 5;
+        let cents = integer
+            .checked_mul(100)
+            .and_then(|x| x.checked_add(tenths * 10 + hundredths + round_up as i64))
+            .ok_or_else(error)?;
+        Ok(Money(if negative { -cents } else { cents }))
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        let amount: String = Deserialize::deserialize(deserializer)?;
+        amount.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        Money(self.0 + other.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        Money(self.0 - other.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        self.0 += other.0
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        self.0 -= other.0
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::default(), |a, b| a + b)
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(s: &str) -> i64 {
+        s.parse::<Money>().unwrap().0
+    }
+
+    #[test]
+    fn parses_whole_and_decimal_amounts() {
+        assert_eq!(cents("12"), 1200);
+        assert_eq!(cents("12.5"), 1250);
+        assert_eq!(cents("-12.30"), -1230);
+        assert_eq!(cents("+0.07"), 7);
+        assert_eq!(cents(".5"), 50);
+        assert_eq!(cents(" 3 "), 300);
+    }
+
+    #[test]
+    fn rounds_legacy_floating_point_amounts() {
+        assert_eq!(cents("12.300000000000001"), 1230);
+        assert_eq!(cents("12.299999999999999"), 1230);
+        assert_eq!(cents("-4.005"), -401);
+        assert_eq!(cents("0.994"), 99);
+        assert_eq!(cents("0.995"), 100);
+    }
+
+    #[test]
+    fn rejects_invalid_amounts() {
+        for invalid in ["", "-", ".", "12,50", "1e3", "12.5.0", "abc", "--1"] {
+            assert!(invalid.parse::<Money>().is_err(), "{}", invalid);
+        }
+        assert!("99999999999999999999".parse::<Money>().is_err());
+    }
+
+    #[test]
+    fn shows_two_decimal_places() {
+        assert_eq!(Money(1230).to_string(), "12.30");
+        assert_eq!(Money(-5).to_string(), "-0.05");
+        assert_eq!(format!("{:>7}", Money(-1250)), " -12.50");
+        assert_eq!(cents(&Money(-123456).to_string()), -123456);
+    }
+}
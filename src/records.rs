@@ -0,0 +1,75 @@
+use crate::error::LedgerError;
+use serde::{de::DeserializeOwned, Serialize};
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+
+// Reads all rows of a csv file, a missing file has no rows
+pub fn read_records<T: DeserializeOwned>(filename: &str) -> Result<Vec<T>, Box<dyn Error>> {
+    if !Path::new(filename).exists() {
+        return Ok(Vec::new());
+    }
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(filename)?;
+    let mut records = Vec::new();
+    for result in rdr.deserialize() {
+        let record: T = result.map_err(|e| LedgerError::from_csv(filename, e))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+// Writes into a temporary file that then replaces the file, so that a crash while
+// writing never leaves a half written file behind
+pub fn write_records<T: Serialize>(records: &[T], filename: &str) -> Result<(), Box<dyn Error>> {
+    let temp_filename = format!("{}.tmp", filename);
+    let mut wtr = csv::Writer::from_path(&temp_filename)?;
+    for record in records {
+        wtr.serialize(record)?;
+    }
+    wtr.into_inner()?.sync_all()?;
+    fs::rename(temp_filename, filename)?;
+    Ok(())
+}
+
+// Reads a csv file without header line like the logs, whose rows are only ever appended
+pub fn read_rows<T: DeserializeOwned>(filename: &str) -> Result<Vec<T>, Box<dyn Error>> {
+    if !Path::new(filename).exists() {
+        return Ok(Vec::new());
+    }
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(filename)?;
+    let mut rows = Vec::new();
+    for result in rdr.deserialize() {
+        rows.push(result.map_err(|e| LedgerError::from_csv(filename, e))?);
+    }
+    Ok(rows)
+}
+
+pub fn append_rows<T: Serialize>(rows: &[T], filename: &str) -> Result<(), Box<dyn Error>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(filename)?;
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(file);
+    for row in rows {
+        wtr.serialize(row)?;
+    }
+    wtr.into_inner()?.sync_all()?;
+    Ok(())
+}
+
+// Replaces all rows of a csv file without header line the same way as write_records
+pub fn write_rows<T: Serialize>(rows: &[T], filename: &str) -> Result<(), Box<dyn Error>> {
+    let temp_filename = format!("{}.tmp", filename);
+    if Path::new(&temp_filename).exists() {
+        fs::remove_file(&temp_filename)?;
+    }
+    append_rows(rows, &temp_filename)?;
+    fs::rename(temp_filename, filename)?;
+    Ok(())
+}
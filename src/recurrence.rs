@@ -0,0 +1,278 @@
+use crate::transaction::{Exception, Recurrence, Repeat, RepeatEnd, Transaction};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::HashSet;
+
+// Returns the last day of the given month
+fn last_day_of_month(year: i32, month: u32) -> NaiveDate {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd(next_year, next_month, 1) - Duration::days(1)
+}
+
+// Adds months to a date, days that do not exist in the target month are moved
+// to the last day of that month (e.g. 31st of January + 1 month -> 28th of February)
+fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    let total = date.year() * 12 + date.month0() as i32 + months as i32;
+    let year = total.div_euclid(12);
+    let month = total.rem_euclid(12) as u32 + 1;
+    let last_day = last_day_of_month(year, month);
+    NaiveDate::from_ymd(year, month, date.day().min(last_day.day()))
+}
+
+// Number of months between the month of `from` and the given month
+fn months_between(from: NaiveDate, year: i32, month: u32) -> i32 {
+    (year * 12 + month as i32 - 1) - (from.year() * 12 + from.month0() as i32)
+}
+
+// Dates of all occurrences (excluding the original entry) that fall into the given month
+// together with their number, the original entry being number 0
+fn occurrence_dates_in_month(
+    start: NaiveDate,
+    repeat: &Repeat,
+    year: i32,
+    month: u32,
+) -> Vec<(u32, NaiveDate)> {
+    let first_day = NaiveDate::from_ymd(year, month, 1);
+    let last_day = last_day_of_month(year, month);
+    if last_day <= start {
+        return Vec::new();
+    }
+    match *repeat {
+        Repeat::Day(n) | Repeat::Week(n) if n > 0 => {
+            let step = match repeat {
+                Repeat::Week(_) => 7 * n as i64,
+                _ => n as i64,
+            };
+            let days_until_month = (first_day - start).num_days();
+            // the first occurrence is always one step after the original entry
+            let mut k = if days_until_month > 0 {
+                (days_until_month + step - 1) / step
+            } else {
+                1
+            }
+            .max(1);
+            let mut dates = Vec::new();
+            loop {
+                let date = start + Duration::days(k * step);
+                if date > last_day {
+                    break;
+                }
+                dates.push((k as u32, date));
+                k += 1;
+            }
+            dates
+        }
+        Repeat::Month(n) | Repeat::Year(n) if n > 0 => {
+            let step = match repeat {
+                Repeat::Year(_) => 12 * n as i32,
+                _ => n as i32,
+            };
+            let diff = months_between(start, year, month);
+            if diff <= 0 || diff % step != 0 {
+                return Vec::new();
+            }
+            vec![((diff / step) as u32, add_months(start, diff as u32))]
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Checks whether the n-th occurrence on the given date is before the end of the recurrence
+fn is_before_end(end: &RepeatEnd, number: u32, date: NaiveDate) -> bool {
+    match end {
+        RepeatEnd::Never => true,
+        RepeatEnd::Until(until) => date <= *until,
+        // the count includes the original entry
+        RepeatEnd::Count(count) => number < *count,
+    }
+}
+
+// Applies the exceptions of a recurrence to an occurrence, None if it is skipped
+fn apply_exceptions(recurrence: &Recurrence, mut occurrence: Transaction) -> Option<Transaction> {
+    for exception in &recurrence.exceptions {
+        match exception {
+            Exception::Skip(date) if *date == occurrence.date => return None,
+            Exception::Change(date, amount) if *date == occurrence.date => {
+                occurrence.amount = *amount
+            }
+            _ => {}
+        }
+    }
+    Some(occurrence)
+}
+
+// Identifies one occurrence of a repeating transaction by the id of the template and the
+// number of the occurrence, generated entries store this to link back to their template
+fn get_origin(template: &Transaction, number: u32) -> String {
+    format!("{}#{}", template.id, number)
+}
+
+// Id of the template and number of the occurrence an entry was generated from
+pub fn parse_origin(origin: &str) -> Option<(&str, u32)> {
+    let (id, number) = origin.rsplit_once('#')?;
+    Some((id, number.parse().ok()?))
+}
+
+// Date of the n-th occurrence of a repeating transaction before exceptions are applied
+pub fn get_occurrence_date(transaction: &Transaction, number: u32) -> Option<NaiveDate> {
+    let date = transaction.date;
+    match transaction.repeat.interval {
+        Repeat::Day(n) => Some(date + Duration::days(n as i64 * number as i64)),
+        Repeat::Week(n) => Some(date + Duration::days(7 * n as i64 * number as i64)),
+        Repeat::Month(n) => Some(add_months(date, n * number)),
+        Repeat::Year(n) => Some(add_months(date, 12 * n * number)),
+        Repeat::None => None,
+    }
+}
+
+// All occurrences of a repeating transaction in the given month, leaving out those whose
+// origin is in `generated` since they are stored as entries already
+pub fn occurrences_in_month(
+    transaction: &Transaction,
+    year: i32,
+    month: u32,
+    generated: &HashSet<String>,
+) -> Vec<Transaction> {
+    let recurrence = &transaction.repeat;
+    occurrence_dates_in_month(transaction.date, &recurrence.interval, year, month)
+        .into_iter()
+        .filter(|(number, date)| is_before_end(&recurrence.end, *number, *date))
+        .filter(|(number, _)| !generated.contains(&get_origin(transaction, *number)))
+        .filter_map(|(number, date)| {
+            apply_exceptions(
+                recurrence,
+                Transaction {
+                    date,
+                    origin: get_origin(transaction, number),
+                    // occurrences are not stored, so they do not share the id of the template
+                    id: String::new(),
+                    ..transaction.clone()
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::get_repeat_from_str;
+
+    fn template(date: NaiveDate, repeat: &str) -> Transaction {
+        Transaction {
+            date,
+            amount: "-900".parse().unwrap(),
+            description: "Rent".to_string(),
+            repeat: get_repeat_from_str(repeat).unwrap(),
+            id: "rent".to_string(),
+            ..Transaction::default()
+        }
+    }
+
+    fn dates(transaction: &Transaction, year: i32, month: u32) -> Vec<NaiveDate> {
+        occurrences_in_month(transaction, year, month, &HashSet::new())
+            .into_iter()
+            .map(|x| x.date)
+            .collect()
+    }
+
+    #[test]
+    fn monthly_occurrences_are_clamped_to_the_end_of_the_month() {
+        let rent = template(NaiveDate::from_ymd(2026, 1, 31), "1m");
+        assert_eq!(dates(&rent, 2026, 1), vec![]);
+        assert_eq!(
+            dates(&rent, 2026, 2),
+            vec![NaiveDate::from_ymd(2026, 2, 28)]
+        );
+        // the day is taken from the original entry, not from the previous occurrence
+        assert_eq!(
+            dates(&rent, 2026, 3),
+            vec![NaiveDate::from_ymd(2026, 3, 31)]
+        );
+        assert_eq!(
+            dates(&rent, 2028, 2),
+            vec![NaiveDate::from_ymd(2028, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn yearly_occurrences_on_leap_days() {
+        let birthday = template(NaiveDate::from_ymd(2024, 2, 29), "1y");
+        assert_eq!(
+            dates(&birthday, 2025, 2),
+            vec![NaiveDate::from_ymd(2025, 2, 28)]
+        );
+        assert_eq!(
+            dates(&birthday, 2028, 2),
+            vec![NaiveDate::from_ymd(2028, 2, 29)]
+        );
+        assert_eq!(dates(&birthday, 2025, 3), vec![]);
+    }
+
+    #[test]
+    fn weekly_occurrences_cross_months() {
+        let groceries = template(NaiveDate::from_ymd(2026, 1, 28), "1w");
+        assert_eq!(
+            dates(&groceries, 2026, 2),
+            vec![
+                NaiveDate::from_ymd(2026, 2, 4),
+                NaiveDate::from_ymd(2026, 2, 11),
+                NaiveDate::from_ymd(2026, 2, 18),
+                NaiveDate::from_ymd(2026, 2, 25),
+            ]
+        );
+    }
+
+    #[test]
+    fn times_counts_the_original_entry() {
+        let rent = template(NaiveDate::from_ymd(2026, 1, 1), "1m;times=3");
+        assert_eq!(dates(&rent, 2026, 2).len(), 1);
+        assert_eq!(dates(&rent, 2026, 3).len(), 1);
+        assert_eq!(dates(&rent, 2026, 4), vec![]);
+    }
+
+    #[test]
+    fn until_includes_its_date() {
+        let rent = template(NaiveDate::from_ymd(2026, 1, 15), "1m;until=2026-03-15");
+        assert_eq!(
+            dates(&rent, 2026, 3),
+            vec![NaiveDate::from_ymd(2026, 3, 15)]
+        );
+        let rent = template(NaiveDate::from_ymd(2026, 1, 15), "1m;until=2026-03-14");
+        assert_eq!(dates(&rent, 2026, 3), vec![]);
+    }
+
+    #[test]
+    fn exceptions_skip_and_change_occurrences() {
+        let rent = template(
+            NaiveDate::from_ymd(2026, 1, 1),
+            "1m;skip=2026-02-01;change=2026-03-01:950",
+        );
+        assert_eq!(dates(&rent, 2026, 2), vec![]);
+        let march = occurrences_in_month(&rent, 2026, 3, &HashSet::new());
+        assert_eq!(march[0].amount, "950".parse().unwrap());
+    }
+
+    #[test]
+    fn generated_occurrences_stay_linked_when_the_template_changes() {
+        let mut rent = template(NaiveDate::from_ymd(2026, 1, 31), "1m");
+        let february = occurrences_in_month(&rent, 2026, 2, &HashSet::new());
+        let generated: HashSet<String> = february.iter().map(|x| x.origin.clone()).collect();
+        rent.description = "Rent flat".to_string();
+        assert!(occurrences_in_month(&rent, 2026, 2, &generated).is_empty());
+        assert_eq!(occurrences_in_month(&rent, 2026, 3, &generated).len(), 1);
+    }
+
+    #[test]
+    fn origins_point_back_to_the_occurrence() {
+        let rent = template(NaiveDate::from_ymd(2026, 1, 31), "1m");
+        let april = occurrences_in_month(&rent, 2026, 4, &HashSet::new());
+        let (id, number) = parse_origin(&april[0].origin).unwrap();
+        assert_eq!((id, number), ("rent", 3));
+        assert_eq!(get_occurrence_date(&rent, number), Some(april[0].date));
+        assert_eq!(parse_origin("rent"), None);
+    }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+
+// Splits a list like "groceries, shared" or "groceries shared" into its entries
+pub fn string_to_set(s: &str) -> HashSet<String> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string())
+        .collect()
+}
+
+// Joins the entries sorted, so that the written files do not change randomly
+pub fn set_to_string(set: &HashSet<String>) -> String {
+    let mut entries: Vec<&str> = set.iter().map(|x| &x[..]).collect();
+    entries.sort_unstable();
+    entries.join(" ")
+}
+
+pub fn serialize<S: Serializer>(set: &HashSet<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    set_to_string(set).serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashSet<String>, D::Error> {
+    let set: String = Deserialize::deserialize(deserializer)?;
+    Ok(string_to_set(&set))
+}
@@ -0,0 +1,254 @@
+use crate::backup;
+use crate::error::LedgerError;
+use crate::records;
+use crate::storage::Storage;
+use crate::transaction::{self, Transaction};
+use chrono::{Datelike, NaiveDate};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+// Year and month of an entry, the entries of that month and the position of the entry in them
+type Location = (u32, u32, Vec<Transaction>, usize);
+
+// One csv file per month in BASE_PATH/YYYY/MM.csv
+pub struct CsvStorage {
+    base_path: String,
+    // months that were backed up already, a storage is opened per command, so that a command
+    // writing a month several times keeps the version from before it
+    backed_up: HashSet<(u32, u32)>,
+}
+
+impl CsvStorage {
+    pub fn new(base_path: &str) -> CsvStorage {
+        CsvStorage {
+            base_path: base_path.to_string(),
+            backed_up: HashSet::new(),
+        }
+    }
+
+    fn get_filename(&self, year: u32, month: u32) -> String {
+        format!("{}/{}/{:0>2}.csv", self.base_path, year, month)
+    }
+
+    fn read_month(&self, year: u32, month: u32) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        read_month_file(&self.get_filename(year, month))
+    }
+
+    fn write_month(
+        &mut self,
+        year: u32,
+        month: u32,
+        transactions: &mut [Transaction],
+    ) -> Result<(), Box<dyn Error>> {
+        let filename = self.get_filename(year, month);
+        if self.backed_up.insert((year, month)) {
+            backup::save(&self.base_path, year, month, &filename)?;
+        }
+        // months without entries are not listed anymore
+        if transactions.is_empty() {
+            if Path::new(&filename).exists() {
+                fs::remove_file(filename)?;
+            }
+            return Ok(());
+        }
+        if let Some(dir) = Path::new(&filename).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        transactions.sort();
+        records::write_records(transactions, &filename)
+    }
+
+    fn lock(&self) -> Result<File, Box<dyn Error>> {
+        lock_data_dir(&self.base_path)
+    }
+
+    // Finds the month and the position of an entry, searching the latest months first,
+    // months whose files cannot be read are skipped
+    fn search(&self, id: &str) -> Result<Option<Location>, Box<dyn Error>> {
+        for (year, month) in self.months()?.into_iter().rev() {
+            let transactions = match self.read_month(year, month) {
+                Ok(transactions) => transactions,
+                Err(_) => continue,
+            };
+            if let Some(index) = transactions.iter().position(|x| x.id == id) {
+                return Ok(Some((year, month, transactions, index)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn find(&self, id: &str) -> Result<Location, Box<dyn Error>> {
+        self.search(id)?
+            .ok_or_else(|| LedgerError::EntryNotFound(id.to_string()).into())
+    }
+}
+
+// Advisory lock on the data directory that is held until the file is dropped,
+// so that two programs do not change the same month at the same time
+pub fn lock_data_dir(base_path: &str) -> Result<File, Box<dyn Error>> {
+    fs::create_dir_all(base_path)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(format!("{}/.lock", base_path))?;
+    file.lock()?;
+    Ok(file)
+}
+
+// Reads the entries of a month file or of a backup of it sorted by date
+pub fn read_month_file(filename: &str) -> Result<Vec<Transaction>, Box<dyn Error>> {
+    // If file does not exists -> no transactions for this month
+    if !Path::new(filename).exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(filename)?;
+    let mut transactions = Vec::new();
+    for (index, result) in rdr.deserialize().enumerate() {
+        let mut record: Transaction = result.map_err(|e| LedgerError::from_csv(filename, e))?;
+        // rows of older files have no id, they get one that stays the same until
+        // the file is written the next time, which stores it
+        if record.id.is_empty() {
+            record.id = get_row_id(&record, index);
+        }
+        transactions.push(record);
+    }
+    transactions.sort();
+    Ok(transactions)
+}
+
+// Id of a row without one, derived from its content and position in the file
+fn get_row_id(transaction: &Transaction, index: usize) -> String {
+    let mut hasher = DefaultHasher::new();
+    transaction.date.hash(&mut hasher);
+    transaction.amount.hash(&mut hasher);
+    transaction.description.hash(&mut hasher);
+    index.hash(&mut hasher);
+    format!("{:x}", hasher.finish())
+}
+
+impl Storage for CsvStorage {
+    fn months(&self) -> Result<Vec<(u32, u32)>, Box<dyn Error>> {
+        let mut result = Vec::new();
+        // a ledger without entries may not have its directory yet
+        if !Path::new(&self.base_path).exists() {
+            return Ok(result);
+        }
+        for entry in fs::read_dir(&self.base_path)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let year = match path.file_name().and_then(|x| x.to_str()).map(str::parse) {
+                Some(Ok(year)) => year,
+                _ => continue,
+            };
+            for month in fs::read_dir(path)? {
+                let month_path = month?.path();
+                if month_path.is_dir() {
+                    continue;
+                }
+                if let Some(Ok(month)) = month_path
+                    .file_stem()
+                    .and_then(|x| x.to_str())
+                    .map(str::parse)
+                {
+                    result.push((year, month));
+                }
+            }
+        }
+        result.sort();
+        Ok(result)
+    }
+
+    fn load(&self, from: NaiveDate, until: NaiveDate) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let first = (from.year() as i64, from.month());
+        let last = (until.year() as i64, until.month());
+        let mut transactions = Vec::new();
+        for (year, month) in self.months()? {
+            if (year as i64, month) < first || (year as i64, month) > last {
+                continue;
+            }
+            transactions.extend(
+                self.read_month(year, month)?
+                    .into_iter()
+                    .filter(|x| x.date >= from && x.date <= until),
+            );
+        }
+        Ok(transactions)
+    }
+
+    // Months whose files cannot be read are skipped, they show their error when they are loaded
+    fn get(&self, id: &str) -> Result<Option<Transaction>, Box<dyn Error>> {
+        Ok(self
+            .search(id)?
+            .map(|(_, _, mut transactions, index)| transactions.swap_remove(index)))
+    }
+
+    // Only looks at the repeat column, so that a malformed value in another column of an
+    // earlier month does not keep the occurrences of later months from being shown
+    fn first_repeating_month(&self) -> Result<Option<(u32, u32)>, Box<dyn Error>> {
+        for (year, month) in self.months()? {
+            let filename = self.get_filename(year, month);
+            let mut rdr = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_path(&filename)?;
+            let column = match rdr.headers()?.iter().position(|x| x == "repeat") {
+                Some(column) => column,
+                None => continue,
+            };
+            for result in rdr.records() {
+                let record = result.map_err(|e| LedgerError::from_csv(&filename, e))?;
+                if record.get(column).is_some_and(|x| !x.is_empty()) {
+                    return Ok(Some((year, month)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn insert(&mut self, mut transaction: Transaction) -> Result<String, Box<dyn Error>> {
+        if transaction.id.is_empty() {
+            transaction.id = transaction::new_id();
+        }
+        let id = transaction.id.clone();
+        let _lock = self.lock()?;
+        let (year, month) = (transaction.date.year() as u32, transaction.date.month());
+        let mut transactions = self.read_month(year, month)?;
+        transactions.push(transaction);
+        self.write_month(year, month, &mut transactions)?;
+        Ok(id)
+    }
+
+    fn update(&mut self, transaction: Transaction) -> Result<(), Box<dyn Error>> {
+        let _lock = self.lock()?;
+        let (year, month, mut transactions, index) = self.find(&transaction.id)?;
+        let (new_year, new_month) = (transaction.date.year() as u32, transaction.date.month());
+        if (new_year, new_month) == (year, month) {
+            transactions[index] = transaction;
+            return self.write_month(year, month, &mut transactions);
+        }
+        // both months are read before anything is written and the new month is written
+        // first, so that a failed write never loses the entry
+        let mut new_transactions = self.read_month(new_year, new_month)?;
+        transactions.remove(index);
+        new_transactions.push(transaction);
+        self.write_month(new_year, new_month, &mut new_transactions)?;
+        self.write_month(year, month, &mut transactions)
+    }
+
+    fn delete(&mut self, id: &str) -> Result<Transaction, Box<dyn Error>> {
+        let _lock = self.lock()?;
+        let (year, month, mut transactions, index) = self.find(id)?;
+        let removed = transactions.remove(index);
+        self.write_month(year, month, &mut transactions)?;
+        Ok(removed)
+    }
+}
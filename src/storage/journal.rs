@@ -0,0 +1,388 @@
+use crate::error::LedgerError;
+use crate::records;
+use crate::storage::{self, Storage};
+use crate::transaction::{self, Transaction};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+const UNDO_JOURNAL: &str = "undo";
+const REDO_JOURNAL: &str = "redo";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Change {
+    Insert,
+    Delete,
+    // an update is stored as the entry before and after it
+    Before,
+    After,
+}
+
+// One stored change, changes made through the same opened storage form a group that
+// is undone and redone as a whole
+type Row = (String, Change, Transaction);
+
+// Records every change into the undo journal of the data directory, so that it can be
+// undone later, also by another session. Every storage has its own journal, since its
+// changes can only be undone in it
+pub struct JournaledStorage {
+    inner: Box<dyn Storage>,
+    base_path: String,
+    name: String,
+    group: String,
+}
+
+impl JournaledStorage {
+    pub fn new(inner: Box<dyn Storage>, base_path: &str, name: &str) -> JournaledStorage {
+        JournaledStorage {
+            inner,
+            base_path: base_path.to_string(),
+            name: name.to_string(),
+            group: transaction::new_id(),
+        }
+    }
+
+    fn record(&self, changes: Vec<(Change, Transaction)>) -> Result<(), Box<dyn Error>> {
+        let _lock = storage::lock_data_dir(&self.base_path)?;
+        let rows: Vec<Row> = changes
+            .into_iter()
+            .map(|(change, transaction)| (self.group.clone(), change, transaction))
+            .collect();
+        records::append_rows(
+            &rows,
+            &get_filename(&self.base_path, UNDO_JOURNAL, &self.name),
+        )?;
+        // a new change cannot be combined with changes that were undone before it
+        let redo = get_filename(&self.base_path, REDO_JOURNAL, &self.name);
+        if Path::new(&redo).exists() {
+            fs::remove_file(redo)?;
+        }
+        Ok(())
+    }
+}
+
+impl Storage for JournaledStorage {
+    fn months(&self) -> Result<Vec<(u32, u32)>, Box<dyn Error>> {
+        self.inner.months()
+    }
+
+    fn load(&self, from: NaiveDate, until: NaiveDate) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        self.inner.load(from, until)
+    }
+
+    fn insert(&mut self, mut transaction: Transaction) -> Result<String, Box<dyn Error>> {
+        transaction.id = self.inner.insert(transaction.clone())?;
+        let id = transaction.id.clone();
+        self.record(vec![(Change::Insert, transaction)])?;
+        Ok(id)
+    }
+
+    fn update(&mut self, transaction: Transaction) -> Result<(), Box<dyn Error>> {
+        let before = self
+            .inner
+            .get(&transaction.id)?
+            .ok_or_else(|| LedgerError::EntryNotFound(transaction.id.clone()))?;
+        self.inner.update(transaction.clone())?;
+        self.record(vec![(Change::Before, before), (Change::After, transaction)])
+    }
+
+    fn delete(&mut self, id: &str) -> Result<Transaction, Box<dyn Error>> {
+        let removed = self.inner.delete(id)?;
+        self.record(vec![(Change::Delete, removed.clone())])?;
+        Ok(removed)
+    }
+
+    fn version(&self, year: u32, month: u32) -> Result<u64, Box<dyn Error>> {
+        self.inner.version(year, month)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Transaction>, Box<dyn Error>> {
+        self.inner.get(id)
+    }
+
+    fn first_repeating_month(&self) -> Result<Option<(u32, u32)>, Box<dyn Error>> {
+        self.inner.first_repeating_month()
+    }
+}
+
+// Journal of a storage like .undo-csv.csv
+fn get_filename(base_path: &str, journal: &str, name: &str) -> String {
+    format!("{}/.{}-{}.csv", base_path, journal, name)
+}
+
+// Removes the last group of changes from the rows of a journal and returns it
+fn split_last_group(rows: &mut Vec<Row>) -> Vec<Row> {
+    let group = match rows.last() {
+        Some((group, _, _)) => group.clone(),
+        None => return Vec::new(),
+    };
+    let start = rows
+        .iter()
+        .rposition(|(x, _, _)| *x != group)
+        .map_or(0, |x| x + 1);
+    rows.split_off(start)
+}
+
+// Short description of a group like "deleting 2026-03-01 Rent"
+fn describe(rows: &[Row]) -> String {
+    let (_, change, transaction) = &rows[0];
+    let action = match change {
+        Change::Insert => "adding",
+        Change::Delete => "deleting",
+        Change::Before | Change::After => "changing",
+    };
+    let amount_entries = rows.iter().filter(|(_, x, _)| *x != Change::After).count();
+    let mut description = format!(
+        "{} {} {}",
+        action, transaction.date, transaction.description
+    );
+    if amount_entries > 1 {
+        description += &format!(" ({} entries)", amount_entries);
+    }
+    description
+}
+
+// Last group of changes of a journal, empty if there is none
+fn get_last_group(filename: &str) -> Result<Vec<Row>, Box<dyn Error>> {
+    Ok(split_last_group(&mut records::read_rows(filename)?))
+}
+
+// Moves an applied group of changes from one journal to the other
+fn move_group(base_path: &str, from: &str, to: &str, group: &[Row]) -> Result<(), Box<dyn Error>> {
+    let _lock = storage::lock_data_dir(base_path)?;
+    // another session may have added changes in the meantime, only this group is removed
+    let rows: Vec<Row> = records::read_rows(from)?
+        .into_iter()
+        .filter(|(x, _, _)| *x != group[0].0)
+        .collect();
+    records::write_rows(&rows, from)?;
+    records::append_rows(group, to)
+}
+
+// Ids of all stored entries, changes to entries that were removed or added again since,
+// e.g. by editing the files, are skipped instead of failing the whole group
+fn get_ids(storage: &dyn Storage) -> Result<HashSet<String>, Box<dyn Error>> {
+    Ok(storage.load_all()?.into_iter().map(|x| x.id).collect())
+}
+
+fn apply_undo(storage: &mut dyn Storage, rows: &[Row]) -> Result<(), Box<dyn Error>> {
+    let mut ids = get_ids(storage)?;
+    for (_, change, transaction) in rows.iter().rev() {
+        match change {
+            Change::Insert if ids.remove(&transaction.id) => {
+                storage.delete(&transaction.id)?;
+            }
+            Change::Delete if ids.insert(transaction.id.clone()) => {
+                storage.insert(transaction.clone())?;
+            }
+            Change::Before if ids.contains(&transaction.id) => {
+                storage.update(transaction.clone())?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn apply_redo(storage: &mut dyn Storage, rows: &[Row]) -> Result<(), Box<dyn Error>> {
+    let mut ids = get_ids(storage)?;
+    for (_, change, transaction) in rows {
+        match change {
+            Change::Insert if ids.insert(transaction.id.clone()) => {
+                storage.insert(transaction.clone())?;
+            }
+            Change::Delete if ids.remove(&transaction.id) => {
+                storage.delete(&transaction.id)?;
+            }
+            Change::After if ids.contains(&transaction.id) => {
+                storage.update(transaction.clone())?
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Reverts the last group of changes of the selected storage, returns its description or
+// None if there is nothing to undo
+pub fn undo() -> Result<Option<String>, Box<dyn Error>> {
+    // the changes themselves are not recorded again
+    undo_in(
+        &mut *storage::open_backend()?,
+        &transaction::get_base_path()?,
+        &storage::get_storage_name(),
+    )
+}
+
+fn undo_in(
+    storage: &mut dyn Storage,
+    base_path: &str,
+    name: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let (undo, redo) = (
+        get_filename(base_path, UNDO_JOURNAL, name),
+        get_filename(base_path, REDO_JOURNAL, name),
+    );
+    let last = get_last_group(&undo)?;
+    if last.is_empty() {
+        return Ok(None);
+    }
+    apply_undo(storage, &last)?;
+    move_group(base_path, &undo, &redo, &last)?;
+    Ok(Some(describe(&last)))
+}
+
+// Makes the last undone group of changes of the selected storage again
+pub fn redo() -> Result<Option<String>, Box<dyn Error>> {
+    redo_in(
+        &mut *storage::open_backend()?,
+        &transaction::get_base_path()?,
+        &storage::get_storage_name(),
+    )
+}
+
+fn redo_in(
+    storage: &mut dyn Storage,
+    base_path: &str,
+    name: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let (undo, redo) = (
+        get_filename(base_path, UNDO_JOURNAL, name),
+        get_filename(base_path, REDO_JOURNAL, name),
+    );
+    let last = get_last_group(&redo)?;
+    if last.is_empty() {
+        return Ok(None);
+    }
+    apply_redo(storage, &last)?;
+    move_group(base_path, &redo, &undo, &last)?;
+    Ok(Some(describe(&last)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::CsvStorage;
+
+    fn entry(date: &str, description: &str) -> Transaction {
+        Transaction {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            amount: "-900".parse().unwrap(),
+            description: description.to_string(),
+            ..Transaction::default()
+        }
+    }
+
+    // Csv storage of the directory whose changes are recorded, a new one for every command
+    fn open(base_path: &str) -> JournaledStorage {
+        JournaledStorage::new(Box::new(CsvStorage::new(base_path)), base_path, "csv")
+    }
+
+    fn undo(base_path: &str) -> Option<String> {
+        undo_in(&mut CsvStorage::new(base_path), base_path, "csv").unwrap()
+    }
+
+    fn redo(base_path: &str) -> Option<String> {
+        redo_in(&mut CsvStorage::new(base_path), base_path, "csv").unwrap()
+    }
+
+    fn get_entries(base_path: &str) -> Vec<(NaiveDate, String)> {
+        CsvStorage::new(base_path)
+            .load_all()
+            .unwrap()
+            .into_iter()
+            .map(|x| (x.date, x.description))
+            .collect()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn undoes_and_redoes_an_insert() {
+        let dir = storage::get_test_dir();
+        open(&dir).insert(entry("2026-03-01", "Rent")).unwrap();
+        assert_eq!(undo(&dir).as_deref(), Some("adding 2026-03-01 Rent"));
+        assert!(get_entries(&dir).is_empty());
+        assert_eq!(undo(&dir), None);
+        assert_eq!(redo(&dir).as_deref(), Some("adding 2026-03-01 Rent"));
+        assert_eq!(get_entries(&dir), vec![(date("2026-03-01"), "Rent".into())]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn undoes_and_redoes_an_update_into_another_month() {
+        let dir = storage::get_test_dir();
+        let id = open(&dir).insert(entry("2026-03-01", "Rent")).unwrap();
+        let moved = Transaction {
+            id,
+            ..entry("2026-04-01", "Rent flat")
+        };
+        open(&dir).update(moved).unwrap();
+        assert_eq!(undo(&dir).as_deref(), Some("changing 2026-03-01 Rent"));
+        assert_eq!(get_entries(&dir), vec![(date("2026-03-01"), "Rent".into())]);
+        redo(&dir);
+        assert_eq!(
+            get_entries(&dir),
+            vec![(date("2026-04-01"), "Rent flat".into())]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn undoes_and_redoes_a_delete() {
+        let dir = storage::get_test_dir();
+        let id = open(&dir).insert(entry("2026-03-01", "Rent")).unwrap();
+        open(&dir).delete(&id).unwrap();
+        assert_eq!(undo(&dir).as_deref(), Some("deleting 2026-03-01 Rent"));
+        assert_eq!(get_entries(&dir), vec![(date("2026-03-01"), "Rent".into())]);
+        redo(&dir);
+        assert!(get_entries(&dir).is_empty());
+        // the insert before it is still there to undo
+        assert_eq!(undo(&dir).as_deref(), Some("deleting 2026-03-01 Rent"));
+        assert_eq!(undo(&dir).as_deref(), Some("adding 2026-03-01 Rent"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn undoes_both_entries_of_a_transfer_at_once() {
+        let dir = storage::get_test_dir();
+        let mut storage = open(&dir);
+        storage.insert(entry("2026-03-01", "Transfer")).unwrap();
+        storage.insert(entry("2026-03-01", "Transfer")).unwrap();
+        assert_eq!(
+            undo(&dir).as_deref(),
+            Some("adding 2026-03-01 Transfer (2 entries)")
+        );
+        assert!(get_entries(&dir).is_empty());
+        redo(&dir);
+        assert_eq!(get_entries(&dir).len(), 2);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn skips_entries_that_are_gone() {
+        let dir = storage::get_test_dir();
+        let id = open(&dir).insert(entry("2026-03-01", "Rent")).unwrap();
+        // like a change in the files that is not recorded
+        CsvStorage::new(&dir).delete(&id).unwrap();
+        assert_eq!(undo(&dir).as_deref(), Some("adding 2026-03-01 Rent"));
+        assert_eq!(undo(&dir), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_a_journal_per_storage() {
+        let dir = storage::get_test_dir();
+        open(&dir).insert(entry("2026-03-01", "Rent")).unwrap();
+        let other = undo_in(&mut CsvStorage::new(&dir), &dir, "log").unwrap();
+        assert_eq!(other, None);
+        assert_eq!(get_entries(&dir).len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+use crate::error::LedgerError;
+use crate::records;
+use crate::storage::{self, Storage};
+use crate::transaction::{self, Transaction};
+use chrono::{Datelike, NaiveDate, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Event {
+    Add,
+    Edit,
+    Delete,
+}
+
+const SNAPSHOT_FILENAME: &str = "feonancials.snapshot.csv";
+
+// Time of the event in UTC, the event and the entry after it (the removed entry for a delete)
+type Row = (String, Event, Transaction);
+
+// Every change is appended as one line to BASE_PATH/feonancials.log, the entries are
+// the snapshot in BASE_PATH/feonancials.snapshot.csv with all events of the log applied
+pub struct LogStorage {
+    base_path: String,
+}
+
+impl LogStorage {
+    pub fn new(base_path: &str) -> LogStorage {
+        LogStorage {
+            base_path: base_path.to_string(),
+        }
+    }
+
+    fn get_log_filename(&self) -> String {
+        format!("{}/feonancials.log", self.base_path)
+    }
+
+    fn get_snapshot_filename(&self) -> String {
+        format!("{}/{}", self.base_path, SNAPSHOT_FILENAME)
+    }
+
+    // All entries by their id
+    fn replay(&self) -> Result<HashMap<String, Transaction>, Box<dyn Error>> {
+        let snapshot: Vec<Transaction> = records::read_records(&self.get_snapshot_filename())?;
+        let mut entries: HashMap<String, Transaction> =
+            snapshot.into_iter().map(|x| (x.id.clone(), x)).collect();
+        // applying an event twice changes nothing, so a log that was already compacted
+        // into the snapshot can be replayed again
+        let rows: Vec<Row> = records::read_rows(&self.get_log_filename())?;
+        for (_, event, transaction) in rows {
+            match event {
+                Event::Add | Event::Edit => {
+                    entries.insert(transaction.id.clone(), transaction);
+                }
+                Event::Delete => {
+                    entries.remove(&transaction.id);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    fn append(&self, event: Event, transaction: Transaction) -> Result<(), Box<dyn Error>> {
+        let time = chrono::offset::Utc::now().to_rfc3339_opts(SecondsFormat::Nanos, true);
+        records::append_rows(&[(time, event, transaction)], &self.get_log_filename())
+    }
+
+    // Writes all entries into the snapshot and empties the log, which drops the history
+    // of the entries
+    pub fn compact(&self) -> Result<usize, Box<dyn Error>> {
+        let _lock = storage::lock_data_dir(&self.base_path)?;
+        let entries = sorted(self.replay()?.into_values());
+        records::write_records(&entries, &self.get_snapshot_filename())?;
+        records::write_rows::<Row>(&[], &self.get_log_filename())?;
+        Ok(entries.len())
+    }
+
+    // Adds the events of another log, e.g. of another computer, events that both logs
+    // have are kept once and the events are ordered by their time
+    pub fn merge(&self, filename: &str) -> Result<usize, Box<dyn Error>> {
+        if !Path::new(filename).exists() {
+            return Err(format!("There is no log {}", filename).into());
+        }
+        // the events of entries that were compacted into a snapshot are gone, so both logs
+        // have to start from the same snapshot, otherwise entries would be lost or deleted
+        // entries would come back
+        let other_snapshot = Path::new(filename).with_file_name(SNAPSHOT_FILENAME);
+        if read_snapshot(&other_snapshot)?
+            != read_snapshot(Path::new(&self.get_snapshot_filename()))?
+        {
+            return Err(format!(
+                "The log {} does not start from the same snapshot as this data directory, \
+                 entries that are only in one snapshot would be lost, copy the whole data \
+                 directory instead and from then on merge in both directions before running \
+                 compact-log on both computers",
+                filename
+            )
+            .into());
+        }
+        let _lock = storage::lock_data_dir(&self.base_path)?;
+        let mut rows: Vec<Row> = records::read_rows(&self.get_log_filename())?;
+        let amount_before = rows.len();
+        let mut known: HashSet<(String, String)> = rows
+            .iter()
+            .map(|(time, _, transaction)| (time.clone(), transaction.id.clone()))
+            .collect();
+        let other: Vec<Row> = records::read_rows(filename)?;
+        for row in other {
+            if known.insert((row.0.clone(), row.2.id.clone())) {
+                rows.push(row);
+            }
+        }
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+        records::write_rows(&rows, &self.get_log_filename())?;
+        Ok(rows.len() - amount_before)
+    }
+}
+
+// Content of a snapshot, which compact always writes the same way for the same entries,
+// a missing snapshot has no entries
+fn read_snapshot(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read(path)?)
+}
+
+// Entries by date, entries of the same day in the order they were added
+fn sorted(entries: impl Iterator<Item = Transaction>) -> Vec<Transaction> {
+    let mut entries: Vec<Transaction> = entries.collect();
+    entries.sort_by(|a, b| a.cmp(b).then_with(|| a.id.cmp(&b.id)));
+    entries
+}
+
+impl Storage for LogStorage {
+    fn months(&self) -> Result<Vec<(u32, u32)>, Box<dyn Error>> {
+        let mut months: Vec<(u32, u32)> = self
+            .replay()?
+            .values()
+            .map(|x| (x.date.year() as u32, x.date.month()))
+            .collect();
+        months.sort();
+        months.dedup();
+        Ok(months)
+    }
+
+    fn load(&self, from: NaiveDate, until: NaiveDate) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let entries = self.replay()?.into_values();
+        Ok(sorted(
+            entries.filter(|x| x.date >= from && x.date <= until),
+        ))
+    }
+
+    fn insert(&mut self, mut transaction: Transaction) -> Result<String, Box<dyn Error>> {
+        if transaction.id.is_empty() {
+            transaction.id = transaction::new_id();
+        }
+        let id = transaction.id.clone();
+        let _lock = storage::lock_data_dir(&self.base_path)?;
+        self.append(Event::Add, transaction)?;
+        Ok(id)
+    }
+
+    fn update(&mut self, transaction: Transaction) -> Result<(), Box<dyn Error>> {
+        let _lock = storage::lock_data_dir(&self.base_path)?;
+        if !self.replay()?.contains_key(&transaction.id) {
+            return Err(LedgerError::EntryNotFound(transaction.id).into());
+        }
+        self.append(Event::Edit, transaction)
+    }
+
+    fn delete(&mut self, id: &str) -> Result<Transaction, Box<dyn Error>> {
+        let _lock = storage::lock_data_dir(&self.base_path)?;
+        let removed = self
+            .replay()?
+            .remove(id)
+            .ok_or_else(|| LedgerError::EntryNotFound(id.to_string()))?;
+        self.append(Event::Delete, removed.clone())?;
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(description: &str) -> Transaction {
+        Transaction {
+            date: NaiveDate::from_ymd(2026, 3, 1),
+            amount: "-900".parse().unwrap(),
+            description: description.to_string(),
+            ..Transaction::default()
+        }
+    }
+
+    fn get_descriptions(storage: &LogStorage) -> Vec<String> {
+        storage
+            .load_all()
+            .unwrap()
+            .into_iter()
+            .map(|x| x.description)
+            .collect()
+    }
+
+    // Second data directory that starts with a copy of the log of the first one
+    fn copy(storage: &LogStorage) -> LogStorage {
+        let other = LogStorage::new(&storage::get_test_dir());
+        fs::copy(storage.get_log_filename(), other.get_log_filename()).unwrap();
+        other
+    }
+
+    #[test]
+    fn replays_the_events_in_their_order() {
+        let mut storage = LogStorage::new(&storage::get_test_dir());
+        let id = storage.insert(entry("Rent")).unwrap();
+        storage.insert(entry("Groceries")).unwrap();
+        let changed = Transaction {
+            id: id.clone(),
+            ..entry("Rent flat")
+        };
+        storage.update(changed).unwrap();
+        storage.delete(&id).unwrap();
+        storage
+            .insert(Transaction {
+                id,
+                ..entry("Rent")
+            })
+            .unwrap();
+        assert_eq!(get_descriptions(&storage), ["Rent", "Groceries"]);
+        // compacting keeps the entries and replaying the old events again changes nothing
+        let rows: Vec<Row> = records::read_rows(&storage.get_log_filename()).unwrap();
+        storage.compact().unwrap();
+        assert_eq!(get_descriptions(&storage), ["Rent", "Groceries"]);
+        records::append_rows(&rows, &storage.get_log_filename()).unwrap();
+        assert_eq!(get_descriptions(&storage), ["Rent", "Groceries"]);
+        fs::remove_dir_all(storage.base_path).unwrap();
+    }
+
+    #[test]
+    fn merges_events_once_ordered_by_time() {
+        let mut storage = LogStorage::new(&storage::get_test_dir());
+        let id = storage.insert(entry("Rent")).unwrap();
+        let mut other = copy(&storage);
+        let changed = |description| Transaction {
+            id: id.clone(),
+            ..entry(description)
+        };
+        other.update(changed("Rent on the other computer")).unwrap();
+        storage.update(changed("Rent flat")).unwrap();
+        // the shared insert is only added once and the later edit wins
+        assert_eq!(storage.merge(&other.get_log_filename()).unwrap(), 1);
+        assert_eq!(get_descriptions(&storage), ["Rent flat"]);
+        assert_eq!(storage.merge(&other.get_log_filename()).unwrap(), 0);
+        assert_eq!(other.merge(&storage.get_log_filename()).unwrap(), 1);
+        assert_eq!(get_descriptions(&other), ["Rent flat"]);
+        fs::remove_dir_all(storage.base_path).unwrap();
+        fs::remove_dir_all(other.base_path).unwrap();
+    }
+
+    #[test]
+    fn refuses_to_merge_logs_of_other_snapshots() {
+        let mut storage = LogStorage::new(&storage::get_test_dir());
+        storage.insert(entry("Rent")).unwrap();
+        let mut other = copy(&storage);
+        other.compact().unwrap();
+        other.insert(entry("Groceries")).unwrap();
+        assert!(storage.merge(&other.get_log_filename()).is_err());
+        assert_eq!(get_descriptions(&storage), ["Rent"]);
+        fs::remove_dir_all(storage.base_path).unwrap();
+        fs::remove_dir_all(other.base_path).unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+use crate::config;
+use crate::transaction::{self, Repeat, Transaction};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::hash::{Hash, Hasher};
+
+mod files;
+mod journal;
+mod log;
+mod sqlite;
+
+pub use files::{lock_data_dir, read_month_file, CsvStorage};
+pub use journal::{redo, undo, JournaledStorage};
+pub use log::LogStorage;
+pub use sqlite::SqliteStorage;
+
+// Where the entries are kept, stored entries are identified by their id
+pub trait Storage {
+    // Months that have entries as (year, month) pairs in ascending order
+    fn months(&self) -> Result<Vec<(u32, u32)>, Box<dyn Error>>;
+
+    // Entries from one date to another (both included) sorted by date
+    fn load(&self, from: NaiveDate, until: NaiveDate) -> Result<Vec<Transaction>, Box<dyn Error>>;
+
+    // Stores a new entry, entries without an id get a new one, returns the id
+    fn insert(&mut self, transaction: Transaction) -> Result<String, Box<dyn Error>>;
+
+    // Replaces the entry with the same id, which may now be in another month
+    fn update(&mut self, transaction: Transaction) -> Result<(), Box<dyn Error>>;
+
+    // Removes the entry with the given id and returns it
+    fn delete(&mut self, id: &str) -> Result<Transaction, Box<dyn Error>>;
+
+    fn load_month(&self, year: u32, month: u32) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let first_day = NaiveDate::from_ymd(year as i32, month, 1);
+        let (next_year, next_month) = transaction::get_next_month(year, month);
+        let last_day = NaiveDate::from_ymd(next_year as i32, next_month, 1) - Duration::days(1);
+        self.load(first_day, last_day)
+    }
+
+    fn load_all(&self) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        self.load(NaiveDate::MIN, NaiveDate::MAX)
+    }
+
+    // Changes whenever an entry of the month changes, so that changes by other programs are
+    // noticed before entries that were loaded earlier are written back
+    fn version(&self, year: u32, month: u32) -> Result<u64, Box<dyn Error>> {
+        let mut wtr = csv::Writer::from_writer(Vec::new());
+        for transaction in self.load_month(year, month)? {
+            wtr.serialize(transaction)?;
+        }
+        let mut hasher = DefaultHasher::new();
+        wtr.into_inner()?.hash(&mut hasher);
+        Ok(hasher.finish())
+    }
+
+    // Gets the entry with the given id, None if there is none
+    fn get(&self, id: &str) -> Result<Option<Transaction>, Box<dyn Error>> {
+        Ok(self.load_all()?.into_iter().find(|x| x.id == id))
+    }
+
+    // Month of the earliest repeating entry, no occurrences fall into the months before it
+    fn first_repeating_month(&self) -> Result<Option<(u32, u32)>, Box<dyn Error>> {
+        Ok(self
+            .load_all()?
+            .iter()
+            .filter(|x| !matches!(x.repeat.interval, Repeat::None))
+            .map(|x| (x.date.year() as u32, x.date.month()))
+            .min())
+    }
+}
+
+// Opens the selected storage, all changes can be undone
+pub fn open() -> Result<Box<dyn Storage>, Box<dyn Error>> {
+    let base_path = transaction::get_base_path()?;
+    Ok(Box::new(JournaledStorage::new(
+        open_backend()?,
+        &base_path,
+        &get_storage_name(),
+    )))
+}
+
+// Name of the storage selected by FEONANCIALS_STORAGE or the config file, csv files by default
+pub fn get_storage_name() -> String {
+    env::var("FEONANCIALS_STORAGE")
+        .ok()
+        .or_else(|| config::get().storage.clone())
+        .unwrap_or_else(|| "csv".to_string())
+}
+
+// Opens the selected storage without recording its changes
+pub fn open_backend() -> Result<Box<dyn Storage>, Box<dyn Error>> {
+    open_named(&get_storage_name(), &transaction::get_base_path()?)
+}
+
+// Opens the storage with the given name ("csv", "sqlite" or "log") in the data directory
+fn open_named(name: &str, base_path: &str) -> Result<Box<dyn Storage>, Box<dyn Error>> {
+    match name {
+        "csv" => Ok(Box::new(CsvStorage::new(base_path))),
+        "sqlite" => Ok(Box::new(SqliteStorage::open(&format!(
+            "{}/feonancials.db",
+            base_path
+        ))?)),
+        "log" => Ok(Box::new(LogStorage::new(base_path))),
+        other => Err(format!("Unknown storage '{}', expected csv, sqlite or log", other).into()),
+    }
+}
+
+// Copies all entries into another storage, from the selected storage if no other is given
+pub fn copy_storage(to: &str, poss_from: &Option<String>) -> Result<(), Box<dyn Error>> {
+    let base_path = transaction::get_base_path()?;
+    let from = poss_from.clone().unwrap_or_else(get_storage_name);
+    if from == to {
+        return Err(format!(
+            "The entries are already in the {} storage, choose the one to copy from with --from",
+            to
+        )
+        .into());
+    }
+    let (from, mut to) = (open_named(&from, &base_path)?, open_named(to, &base_path)?);
+    let existing: HashSet<String> = to.load_all()?.into_iter().map(|x| x.id).collect();
+    let mut amount_copied = 0;
+    for transaction in from.load_all()? {
+        // entries that were copied before are left alone
+        if !existing.contains(&transaction.id) {
+            to.insert(transaction)?;
+            amount_copied += 1;
+        }
+    }
+    println!("Copied {} entries", amount_copied);
+    Ok(())
+}
+
+// New empty data directory for a test
+#[cfg(test)]
+fn get_test_dir() -> String {
+    let dir = env::temp_dir().join(format!("feonancials-test-{}", transaction::new_id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.to_string_lossy().into_owned()
+}
+
+// Writes the entries of the log storage into its snapshot and empties the log
+pub fn compact_log() -> Result<(), Box<dyn Error>> {
+    let amount_entries = LogStorage::new(&transaction::get_base_path()?).compact()?;
+    println!(
+        "Compacted the log into a snapshot of {} entries",
+        amount_entries
+    );
+    Ok(())
+}
+
+// Adds the events of the log of another data directory to the log storage
+pub fn merge_log(filename: &str) -> Result<(), Box<dyn Error>> {
+    let amount_events = LogStorage::new(&transaction::get_base_path()?).merge(filename)?;
+    println!("Merged {} new events", amount_events);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(date: &str, description: &str) -> Transaction {
+        Transaction {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            amount: "-12.50".parse().unwrap(),
+            description: description.to_string(),
+            ..Transaction::default()
+        }
+    }
+
+    fn get_descriptions(storage: &dyn Storage, year: u32, month: u32) -> Vec<String> {
+        storage
+            .load_month(year, month)
+            .unwrap()
+            .into_iter()
+            .map(|x| x.description)
+            .collect()
+    }
+
+    // Every storage behaves the same, also when it is opened again
+    fn check_storage(name: &str) {
+        let dir = get_test_dir();
+        let mut storage = open_named(name, &dir).unwrap();
+        let id = storage.insert(entry("2026-03-05", "Groceries")).unwrap();
+        storage.insert(entry("2026-03-01", "Rent")).unwrap();
+        assert_eq!(get_descriptions(&*storage, 2026, 3), ["Rent", "Groceries"]);
+        assert_eq!(storage.get(&id).unwrap().unwrap().description, "Groceries");
+
+        let moved = Transaction {
+            id: id.clone(),
+            ..entry("2026-04-02", "Groceries")
+        };
+        storage.update(moved).unwrap();
+        let storage = open_named(name, &dir).unwrap();
+        assert_eq!(get_descriptions(&*storage, 2026, 3), ["Rent"]);
+        assert_eq!(get_descriptions(&*storage, 2026, 4), ["Groceries"]);
+        assert_eq!(storage.months().unwrap(), [(2026, 3), (2026, 4)]);
+
+        let mut storage = open_named(name, &dir).unwrap();
+        assert_eq!(storage.delete(&id).unwrap().description, "Groceries");
+        assert!(storage.get(&id).unwrap().is_none());
+        assert_eq!(storage.months().unwrap(), [(2026, 3)]);
+        assert!(storage.delete(&id).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn csv_files_store_entries() {
+        check_storage("csv");
+    }
+
+    #[test]
+    fn sqlite_stores_entries() {
+        check_storage("sqlite");
+    }
+
+    #[test]
+    fn log_stores_entries() {
+        check_storage("log");
+    }
+}
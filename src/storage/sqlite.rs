@@ -0,0 +1,173 @@
+use crate::date_serializer;
+use crate::error::LedgerError;
+use crate::set_serializer;
+use crate::storage::Storage;
+use crate::transaction::{self, Transaction};
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection, Row};
+use std::error::Error;
+
+const COLUMNS: &str = "id, date, amount, description, repeat, category, currency, account, \
+                       transfer, origin, switches, tags";
+
+// All entries in one table of an embedded database, so that queries over many
+// months do not have to read a file per month
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(filename: &str) -> Result<SqliteStorage, Box<dyn Error>> {
+        let connection = Connection::open(filename)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS transactions (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                amount TEXT NOT NULL,
+                description TEXT NOT NULL,
+                repeat TEXT NOT NULL,
+                category TEXT NOT NULL,
+                currency TEXT NOT NULL,
+                account TEXT NOT NULL,
+                transfer TEXT NOT NULL,
+                origin TEXT NOT NULL,
+                switches TEXT NOT NULL,
+                tags TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS transactions_date ON transactions (date);",
+        )?;
+        Ok(SqliteStorage { connection })
+    }
+
+    fn write(&self, statement: &str, transaction: &Transaction) -> Result<usize, Box<dyn Error>> {
+        Ok(self.connection.execute(
+            statement,
+            params![
+                transaction.id,
+                date_serializer::time_to_csv(transaction.date),
+                transaction.amount.to_string(),
+                transaction.description,
+                transaction.repeat.to_string(),
+                transaction.category,
+                transaction.currency,
+                transaction.account,
+                transaction.transfer,
+                transaction.origin,
+                set_serializer::set_to_string(&transaction.switches),
+                set_serializer::set_to_string(&transaction.tags),
+            ],
+        )?)
+    }
+}
+
+fn get_columns(row: &Row) -> rusqlite::Result<Vec<String>> {
+    (0..12).map(|index| row.get(index)).collect()
+}
+
+fn to_transaction(columns: Vec<String>) -> Result<Transaction, Box<dyn Error>> {
+    Ok(Transaction {
+        id: columns[0].clone(),
+        date: date_serializer::string_to_time(&columns[1])?,
+        amount: columns[2].parse()?,
+        description: columns[3].clone(),
+        repeat: transaction::get_repeat_from_str(&columns[4])?,
+        category: columns[5].clone(),
+        currency: columns[6].clone(),
+        account: columns[7].clone(),
+        transfer: columns[8].clone(),
+        origin: columns[9].clone(),
+        switches: set_serializer::string_to_set(&columns[10]),
+        tags: set_serializer::string_to_set(&columns[11]),
+    })
+}
+
+impl Storage for SqliteStorage {
+    fn months(&self) -> Result<Vec<(u32, u32)>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT DISTINCT CAST(substr(date, 1, 4) AS INTEGER), CAST(substr(date, 6, 2) AS INTEGER)
+             FROM transactions ORDER BY 1, 2",
+        )?;
+        let months = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+        Ok(months)
+    }
+
+    fn load(&self, from: NaiveDate, until: NaiveDate) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        // dates are compared as text, which only works for four digit years
+        let from = from.max(NaiveDate::from_ymd(0, 1, 1));
+        let until = until.min(NaiveDate::from_ymd(9999, 12, 31));
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM transactions WHERE date BETWEEN ?1 AND ?2 ORDER BY date, rowid",
+            COLUMNS
+        ))?;
+        let rows = statement.query_map(
+            params![
+                date_serializer::time_to_csv(from),
+                date_serializer::time_to_csv(until)
+            ],
+            get_columns,
+        )?;
+        rows.map(|columns| to_transaction(columns?)).collect()
+    }
+
+    fn insert(&mut self, mut transaction: Transaction) -> Result<String, Box<dyn Error>> {
+        if transaction.id.is_empty() {
+            transaction.id = transaction::new_id();
+        }
+        self.write(
+            &format!(
+                "INSERT INTO transactions ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                COLUMNS
+            ),
+            &transaction,
+        )?;
+        Ok(transaction.id)
+    }
+
+    fn get(&self, id: &str) -> Result<Option<Transaction>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM transactions WHERE id = ?1",
+            COLUMNS
+        ))?;
+        let mut rows = statement.query_map(params![id], get_columns)?;
+        match rows.next() {
+            Some(columns) => Ok(Some(to_transaction(columns?)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn first_repeating_month(&self) -> Result<Option<(u32, u32)>, Box<dyn Error>> {
+        let first: Option<String> = self.connection.query_row(
+            "SELECT MIN(date) FROM transactions WHERE repeat != ''",
+            [],
+            |row| row.get(0),
+        )?;
+        match first {
+            Some(date) => {
+                let date = date_serializer::string_to_time(&date)?;
+                Ok(Some((date.year() as u32, date.month())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn update(&mut self, transaction: Transaction) -> Result<(), Box<dyn Error>> {
+        let statement = "UPDATE transactions SET date = ?2, amount = ?3, description = ?4,
+            repeat = ?5, category = ?6, currency = ?7, account = ?8, transfer = ?9,
+            origin = ?10, switches = ?11, tags = ?12 WHERE id = ?1";
+        match self.write(statement, &transaction)? {
+            0 => Err(LedgerError::EntryNotFound(transaction.id).into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn delete(&mut self, id: &str) -> Result<Transaction, Box<dyn Error>> {
+        let removed = self
+            .get(id)?
+            .ok_or_else(|| LedgerError::EntryNotFound(id.to_string()))?;
+        self.connection
+            .execute("DELETE FROM transactions WHERE id = ?1", params![id])?;
+        Ok(removed)
+    }
+}
@@ -1,16 +1,24 @@
+use crate::account;
+use crate::budget::{self, BudgetStatus};
+use crate::category;
+use crate::config;
 use crate::date_serializer;
+use crate::error::LedgerError;
+use crate::exchange::{get_base_currency, ExchangeRates};
+use crate::locale::{self, Locale};
+use crate::money::Money;
+use crate::recurrence;
+use crate::set_serializer;
+use crate::storage;
 use chrono::{Datelike, NaiveDate};
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
-use std::env;
-use std::env::VarError;
-use std::error::Error;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt;
-use std::fs;
 use std::num::ParseIntError;
-use std::path::Path;
+use std::sync::atomic::{AtomicI64, Ordering as AtomicOrdering};
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Clone)]
 pub enum Repeat {
     Day(u32),
     Week(u32),
@@ -19,24 +27,184 @@ pub enum Repeat {
     None,
 }
 
+impl fmt::Display for Repeat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Repeat::Day(n) => write!(f, "{}d", n),
+            Repeat::Week(n) => write!(f, "{}w", n),
+            Repeat::Month(n) => write!(f, "{}m", n),
+            Repeat::Year(n) => write!(f, "{}y", n),
+            Repeat::None => Ok(()),
+        }
+    }
+}
+
+// When a repeating transaction stops
+#[derive(Debug, Clone)]
+pub enum RepeatEnd {
+    Never,
+    Until(NaiveDate),
+    // total number of entries including the original one
+    Count(u32),
+}
+
+// Changes to single occurrences of a repeating transaction
+#[derive(Debug, Clone)]
+pub enum Exception {
+    Skip(NaiveDate),
+    Change(NaiveDate, Money),
+}
+
+#[derive(Debug, Clone)]
+pub struct Recurrence {
+    pub interval: Repeat,
+    pub end: RepeatEnd,
+    pub exceptions: Vec<Exception>,
+}
+
+impl Default for Recurrence {
+    fn default() -> Recurrence {
+        Recurrence {
+            interval: Repeat::None,
+            end: RepeatEnd::Never,
+            exceptions: Vec::new(),
+        }
+    }
+}
+
+// Uses the same syntax as the command line, e.g. "1m;until=2027-06-30;skip=2026-12-31"
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.interval)?;
+        match self.end {
+            RepeatEnd::Never => {}
+            RepeatEnd::Until(date) => write!(f, ";until={}", date)?,
+            RepeatEnd::Count(count) => write!(f, ";times={}", count)?,
+        }
+        for exception in &self.exceptions {
+            match exception {
+                Exception::Skip(date) => write!(f, ";skip={}", date)?,
+                Exception::Change(date, amount) => write!(f, ";change={}:{}", date, amount)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Recurrence {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_string().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Recurrence, D::Error> {
+        let repeat: String = Deserialize::deserialize(deserializer)?;
+        get_repeat_from_str(&repeat).map_err(de::Error::custom)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Transaction {
     #[serde(with = "date_serializer")]
     pub date: NaiveDate,
-    pub amount: f64,
+    pub amount: Money,
     pub description: String,
-    pub repeat: Repeat,
-    // switches: HashSet<String>,
-    // tags: HashSet<String>,
+    pub repeat: Recurrence,
+    // hierarchical category with subcategories separated by ':', e.g. "Food:Groceries"
+    #[serde(default)]
+    pub category: String,
+    // currency code of the amount, empty for the base currency
+    #[serde(default)]
+    pub currency: String,
+    // entries without an account belong to the default account
+    #[serde(default)]
+    pub account: String,
+    // shared by both entries of a transfer between accounts, empty for other entries
+    #[serde(default)]
+    pub transfer: String,
+    // occurrence of a repeating transaction this entry was generated from
+    #[serde(default)]
+    pub origin: String,
+    #[serde(with = "set_serializer", default)]
+    pub switches: HashSet<String>,
+    #[serde(with = "set_serializer", default)]
+    pub tags: HashSet<String>,
+    // stays the same for the whole life of the entry, unlike its position in the month
+    #[serde(default)]
+    pub id: String,
 }
 
 impl Default for Transaction {
     fn default() -> Transaction {
         Transaction {
             date: chrono::offset::Local::today().naive_local(),
-            amount: 0.0,
+            amount: Money::default(),
             description: String::new(),
-            repeat: Repeat::None,
+            repeat: Recurrence::default(),
+            category: String::new(),
+            currency: String::new(),
+            account: String::new(),
+            transfer: String::new(),
+            origin: String::new(),
+            switches: HashSet::new(),
+            tags: HashSet::new(),
+            id: String::new(),
+        }
+    }
+}
+
+// Id for a new entry, ids of one process are unique even if they are created
+// within the same nanosecond
+pub fn new_id() -> String {
+    static LAST: AtomicI64 = AtomicI64::new(0);
+    let now = chrono::offset::Utc::now().timestamp_nanos();
+    let next = |last: i64| last.max(now - 1) + 1;
+    let last = LAST
+        .fetch_update(AtomicOrdering::SeqCst, AtomicOrdering::SeqCst, |last| {
+            Some(next(last))
+        })
+        .unwrap_or_default();
+    format!("{:x}", next(last))
+}
+
+impl Transaction {
+    // Description followed by the optional properties of the entry
+    pub fn details(&self) -> String {
+        let mut details = self.description.clone();
+        if !self.currency.is_empty() {
+            details += &format!(
+                "\t{}",
+                Locale::current().format_currency(self.amount, &self.currency)
+            );
+        }
+        if !self.account.is_empty() {
+            details += &format!("\t<{}>", self.account);
+        }
+        if self.is_transfer() {
+            details += "\ttransfer";
+        }
+        if !self.category.is_empty() {
+            details += &format!("\t{{{}}}", self.category);
+        }
+        if !self.tags.is_empty() {
+            details += &format!("\t[{}]", set_serializer::set_to_string(&self.tags));
+        }
+        if !self.switches.is_empty() {
+            details += &format!("\t({})", set_serializer::set_to_string(&self.switches));
+        }
+        details
+    }
+
+    pub fn is_transfer(&self) -> bool {
+        !self.transfer.is_empty()
+    }
+
+    pub fn account(&self) -> &str {
+        if self.account.is_empty() {
+            DEFAULT_ACCOUNT
+        } else {
+            &self.account
         }
     }
 }
@@ -65,113 +233,328 @@ impl fmt::Display for Transaction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "{}\t{:>7.2}\t{}",
-            self.date, self.amount, self.description
+            "{}\t{:>7}\t{}",
+            format_date(self.date),
+            locale::format_amount(self.amount),
+            self.details()
         )
     }
 }
 
-fn get_base_path() -> Result<String, VarError> {
-    env::var("FEONANCIALS_PATH")
+pub const REIMBURSABLE: &str = "reimbursable";
+pub const PENDING: &str = "pending";
+pub const CASH: &str = "cash";
+
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+// Balance by account name
+pub type Balances = BTreeMap<String, Money>;
+
+// Restricts which transactions are listed and summed up
+#[derive(Default)]
+pub struct Filter {
+    pub account: Option<String>,
+    pub tags: Vec<String>,
+    pub switches: Vec<String>,
+}
+
+impl Filter {
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        self.account
+            .as_ref()
+            .is_none_or(|account| transaction.account() == account)
+            && self.tags.iter().all(|tag| transaction.tags.contains(tag))
+            && self
+                .switches
+                .iter()
+                .all(|switch| transaction.switches.contains(switch))
+    }
 }
 
-fn get_filename_from_date(year: u32, month: u32) -> Result<String, VarError> {
-    let base_path = get_base_path();
-    Ok(format!("{}/{}/{:0>2}.csv", base_path?, year, month))
+pub fn get_base_path() -> Result<String, LedgerError> {
+    Ok(config::get_data_dir()?)
 }
 
-fn get_transactions(filename: &str) -> Result<Vec<Transaction>, Box<dyn Error>> {
-    let mut transactions = Vec::new();
+// Gets the occurrences of all repeating transactions that fall into the given month
+fn get_occurrences(year: u32, month: u32) -> Result<Vec<Transaction>, LedgerError> {
+    let storage = storage::open()?;
+    // the months before the first repeating entry are not read at all
+    let (first_year, first_month) = match storage.first_repeating_month()? {
+        Some(first) if first <= (year, month) => first,
+        _ => return Ok(Vec::new()),
+    };
+    let entries = load_until_month(
+        &*storage,
+        NaiveDate::from_ymd(first_year as i32, first_month, 1),
+        year,
+        month,
+    )?;
+    Ok(get_occurrences_between(
+        &entries,
+        (year, month),
+        (year, month),
+    ))
+}
 
-    // If file does not exists -> no transactions for this month
-    if !Path::new(filename).exists() {
-        return Ok(Vec::new());
+// Loads the entries from the given date until the end of the given month together with the
+// entries of the later months, which only matter for generated entries that were moved out of
+// their month, so a later month that cannot be read is left out and shows its error when it
+// is listed itself
+fn load_until_month(
+    storage: &dyn storage::Storage,
+    from: NaiveDate,
+    year: u32,
+    month: u32,
+) -> Result<Vec<Transaction>, LedgerError> {
+    let next_month = get_next_month(year, month);
+    let next_first_day = NaiveDate::from_ymd(next_month.0 as i32, next_month.1, 1);
+    let mut entries = storage.load(from, next_first_day.pred())?;
+    for (later_year, later_month) in storage.months()? {
+        if (later_year, later_month) >= next_month {
+            entries.extend(
+                storage
+                    .load_month(later_year, later_month)
+                    .unwrap_or_default(),
+            );
+        }
     }
+    Ok(entries)
+}
 
-    let mut rdr = csv::ReaderBuilder::new()
-        // .has_headers(false)
-        .trim(csv::Trim::All)
-        .from_path(filename)?;
-    for result in rdr.deserialize() {
-        // Notice that we need to provide a type hint for automatic
-        // deserialization.
-        let record: Transaction = result?;
-        // println!("{:?}", record);
-        transactions.push(record);
+// Gets the occurrences of the repeating ones of all stored entries that fall into the months
+// from `first` until `last`, occurrences that were already generated are left out, also if
+// the generated entry was moved into another month
+fn get_occurrences_between(
+    entries: &[Transaction],
+    first: (u32, u32),
+    last: (u32, u32),
+) -> Vec<Transaction> {
+    let generated: HashSet<String> = entries
+        .iter()
+        .map(|x| x.origin.clone())
+        .filter(|x| !x.is_empty())
+        .collect();
+    let mut occurrences = Vec::new();
+    for transaction in entries {
+        if let Repeat::None = transaction.repeat.interval {
+            continue;
+        }
+        let (mut year, mut month) = first;
+        while (year, month) <= last {
+            occurrences.extend(recurrence::occurrences_in_month(
+                transaction,
+                year as i32,
+                month,
+                &generated,
+            ));
+            (year, month) = get_next_month(year, month);
+        }
     }
+    occurrences.sort();
+    occurrences
+}
+
+// Gets the stored transactions of a month together with the occurrences of repeating ones
+fn get_month_entries(year: u32, month: u32) -> Result<Vec<Transaction>, LedgerError> {
+    let mut transactions = storage::open()?.load_month(year, month)?;
+    transactions.extend(get_occurrences(year, month)?);
     Ok(transactions)
 }
 
-fn get_sum_for_month(year: u32, month: u32) -> Result<f64, Box<dyn Error>> {
-    let filename = get_filename_from_date(year, month)?;
-    let transactions = get_transactions(&filename)?;
-    Ok(transactions.into_iter().map(|x| x.amount).sum())
+// Like get_month_entries with all amounts converted into the base currency
+fn get_transactions_with_occurrences(
+    year: u32,
+    month: u32,
+) -> Result<Vec<Transaction>, LedgerError> {
+    Ok(ExchangeRates::load()?.to_base(get_month_entries(year, month)?))
 }
 
-pub fn get_formatted_sum_for_month(date: &NaiveDate) -> Result<String, Box<dyn Error>> {
-    let sum = get_sum_for_month(date.year() as u32, date.month())?;
-    Ok(format!("{:.2}", sum))
+// Warning about an entry of the month without an exchange rate, whose amount is shown
+// unconverted, the menu shows it in its message line
+pub fn get_missing_rate_for_month(date: &NaiveDate) -> Result<Option<String>, LedgerError> {
+    let transactions = get_month_entries(date.year() as u32, date.month())?;
+    Ok(ExchangeRates::load()?.get_missing_rate(&transactions))
 }
 
-fn print_sum_for_month(year: u32, month: u32) -> Result<(), Box<dyn Error>> {
-    let sum = get_sum_for_month(year, month)?;
-    println!("Sum:\t\t{:>7.2}", sum);
+fn warn_missing_rate(date: &NaiveDate) -> Result<(), LedgerError> {
+    if let Some(warning) = get_missing_rate_for_month(date)? {
+        eprintln!("{}", warning);
+    }
     Ok(())
 }
 
-fn print_list(year: u32, month: u32) -> Result<(), Box<dyn Error>> {
-    let filename = get_filename_from_date(year, month)?;
-    let transactions = get_transactions(&filename)?;
-    for (index, transaction) in transactions.iter().enumerate() {
-        println!("{:>3}  {}", index, transaction);
-    }
+// Like get_transactions_with_occurrences, but without transfers, since moving money
+// between accounts is neither income nor expense
+fn get_income_and_expenses(year: u32, month: u32) -> Result<Vec<Transaction>, LedgerError> {
+    let mut transactions = get_transactions_with_occurrences(year, month)?;
+    transactions.retain(|x| !x.is_transfer());
+    Ok(transactions)
+}
+
+fn get_sum_for_month(year: u32, month: u32, filter: &Filter) -> Result<Money, LedgerError> {
+    let transactions = get_income_and_expenses(year, month)?;
+    Ok(transactions
+        .into_iter()
+        .filter(|x| filter.matches(x))
+        .map(|x| x.amount)
+        .sum())
+}
+
+pub fn get_formatted_sum_for_month(date: &NaiveDate) -> Result<String, LedgerError> {
+    let sum = get_sum_for_month(date.year() as u32, date.month(), &Filter::default())?;
+    Ok(Locale::current().format_currency(sum, &get_base_currency()))
+}
+
+fn print_sum_for_month(year: u32, month: u32, filter: &Filter) -> Result<(), LedgerError> {
+    let sum = get_sum_for_month(year, month, filter)?;
+    println!(
+        "Sum:\t\t{:>7}",
+        Locale::current().format_currency(sum, &get_base_currency())
+    );
     Ok(())
 }
 
-fn write_entries(
-    transactions: &mut Vec<Transaction>,
-    filename: String,
-) -> Result<(), Box<dyn Error>> {
-    let mut wtr = csv::Writer::from_path(filename)?;
-    transactions.sort();
-    for transaction in transactions {
-        wtr.serialize(transaction)?;
+fn print_list(year: u32, month: u32, filter: &Filter) -> Result<(), LedgerError> {
+    // the index stays the one in the whole month, so that it can be used for deleting,
+    // repeated entries are not stored in this month and therefore have no index or id
+    let mut entries: Vec<(String, Transaction)> = storage::open()?
+        .load_month(year, month)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, transaction)| (index.to_string(), transaction))
+        .chain(
+            get_occurrences(year, month)?
+                .into_iter()
+                .map(|occurrence| ("*".to_string(), occurrence)),
+        )
+        .collect();
+    entries.sort_by(|a, b| a.1.cmp(&b.1));
+    // amounts and balances are shown in the base currency, the original amount is part of the details
+    let converted =
+        ExchangeRates::load()?.to_base(entries.iter().map(|(_, x)| x.clone()).collect());
+    let balances = get_running_balances(
+        get_balance_before(year, month, filter.account.as_deref())?,
+        &converted,
+        filter.account.as_deref(),
+    );
+    for (((label, transaction), converted), balance) in entries.iter().zip(&converted).zip(balances)
+    {
+        if filter.matches(transaction) {
+            println!(
+                "{:>3}  {}\t{:>7}\t{:>8}\t{:<16}\t{}",
+                label,
+                format_date(transaction.date),
+                locale::format_amount(converted.amount),
+                locale::format_amount(balance),
+                transaction.id,
+                transaction.details()
+            );
+        }
     }
-    wtr.flush()?;
     Ok(())
 }
 
-pub fn write_transactions(transactions: &mut Vec<Transaction>) -> Result<(), Box<dyn Error>> {
-    if transactions.is_empty() {
-        return Ok(());
+// Gets the balance after each of the given entries, only counting entries of the given account
+pub fn get_running_balances<'a>(
+    start: Money,
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+    account: Option<&str>,
+) -> Vec<Money> {
+    let mut balance = start;
+    transactions
+        .into_iter()
+        .map(|transaction| {
+            if account.is_none_or(|account| transaction.account() == account) {
+                balance += transaction.amount;
+            }
+            balance
+        })
+        .collect()
+}
+
+// Replaces the stored entry with the same id
+pub fn update_transaction(transaction: Transaction) -> Result<(), LedgerError> {
+    update_with_partner(&mut *storage::open()?, transaction)
+}
+
+// Updates an entry, the other entry of a transfer keeps the same date, description and amount
+fn update_with_partner(
+    storage: &mut dyn storage::Storage,
+    transaction: Transaction,
+) -> Result<(), LedgerError> {
+    if transaction.is_transfer() {
+        let partners: Vec<Transaction> = storage
+            .load_all()?
+            .into_iter()
+            .filter(|x| x.transfer == transaction.transfer && x.id != transaction.id)
+            .collect();
+        // checked before anything is written, like when the transfer is added
+        if partners
+            .iter()
+            .any(|x| x.account() == transaction.account())
+        {
+            return Err(LedgerError::Other(format!(
+                "A transfer needs two different accounts, both are {}",
+                transaction.account()
+            )));
+        }
+        for mut partner in partners {
+            partner.date = transaction.date;
+            partner.description = transaction.description.clone();
+            partner.amount = -transaction.amount;
+            storage.update(partner)?;
+        }
     }
-    let date = transactions[0].date;
-    let filename = get_filename_from_date(date.year() as u32, date.month())?;
-    write_entries(transactions, filename)
+    Ok(storage.update(transaction)?)
 }
 
-fn add_entry(
-    year: u32,
-    month: u32,
-    day: u32,
-    amount: f64,
-    description: &str,
-    repeat: &str
-) -> Result<(), Box<dyn Error>> {
-    let transaction = Transaction {
-        date: NaiveDate::from_ymd(year as i32, month, day),
-        amount,
-        description: description.to_string(),
-        repeat: get_repeat_from_str(repeat)?
+// Parses a stored repeat like "1m" optionally followed by options separated by ';':
+// "until=DATE", "times=N", "skip=DATE" and "change=DATE:AMOUNT", amounts are plain numbers
+pub fn get_repeat_from_str(repeat: &str) -> Result<Recurrence, LedgerError> {
+    parse_repeat(repeat, str::parse)
+}
+
+// Parses a repeat the same way, the changed amounts are given in the current locale. ';'
+// separates the options as it is neither a decimal nor a thousands separator
+fn get_repeat_from_input(repeat: &str) -> Result<Recurrence, LedgerError> {
+    parse_repeat(repeat, locale::parse_amount)
+}
+
+fn parse_repeat(
+    repeat: &str,
+    parse_amount: fn(&str) -> Result<Money, String>,
+) -> Result<Recurrence, LedgerError> {
+    let mut parts = repeat.split(';').map(str::trim);
+    let mut recurrence = Recurrence {
+        interval: get_interval_from_str(parts.next().unwrap_or_default())?,
+        ..Recurrence::default()
     };
-    let filename = get_filename_from_date(year, month)?;
-    let mut transactions = get_transactions(&filename)?;
-    transactions.push(transaction);
-    write_entries(&mut transactions, filename)
+    for part in parts {
+        match part.split_once('=') {
+            Some(("until", date)) => recurrence.end = RepeatEnd::Until(get_date(date)?),
+            Some(("times", count)) => recurrence.end = RepeatEnd::Count(count.parse()?),
+            Some(("skip", date)) => recurrence.exceptions.push(Exception::Skip(get_date(date)?)),
+            Some(("change", change)) => {
+                let (date, amount) = change
+                    .split_once(':')
+                    .ok_or_else(|| format!("Expected DATE:AMOUNT in '{}'", part))?;
+                recurrence
+                    .exceptions
+                    .push(Exception::Change(get_date(date)?, parse_amount(amount)?));
+            }
+            _ => {
+                return Err(LedgerError::Other(format!(
+                    "Unknown repeat option '{}'",
+                    part
+                )))
+            }
+        }
+    }
+    Ok(recurrence)
 }
 
-fn get_repeat_from_str(repeat: &str) -> Result<Repeat, Box<dyn Error>> {
-    if repeat.len() == 0 {
+fn get_interval_from_str(repeat: &str) -> Result<Repeat, LedgerError> {
+    if repeat.is_empty() {
         return Ok(Repeat::None);
     }
     let res = match repeat.as_bytes()[repeat.len() - 1] as char {
@@ -189,113 +572,643 @@ fn get_amount_from_repeat_str(repeat: &str) -> Result<u32, ParseIntError> {
     trim.parse()
 }
 
-pub fn add_transaction(transaction: Transaction) -> Result<(), Box<dyn Error>> {
-    let filename =
-        get_filename_from_date(transaction.date.year() as u32, transaction.date.month())?;
-    let mut transactions = get_transactions(&filename)?;
-    transactions.push(transaction);
-    write_entries(&mut transactions, filename)
+pub fn add_transaction(transaction: Transaction) -> Result<(), LedgerError> {
+    storage::open()?.insert(transaction)?;
+    Ok(())
+}
+
+// Amounts are expenses unless they start with '+' or are marked as income,
+// an explicit '-' always stays an expense
+pub fn get_signed_amount(input: &str, income: bool) -> Result<Money, String> {
+    let amount = locale::parse_amount(input)?;
+    if income || input.contains('+') || amount < Money::default() {
+        Ok(amount)
+    } else {
+        Ok(-amount)
+    }
+}
+
+// Parses the new amount of a stored entry, like in transfer the amount of a transfer is the
+// one that is moved and the direction of the entry stays
+pub fn get_changed_amount(transaction: &Transaction, input: &str) -> Result<Money, String> {
+    if !transaction.is_transfer() {
+        return get_signed_amount(input, false);
+    }
+    let moved = locale::parse_amount(input)?.abs();
+    if transaction.amount < Money::default() {
+        Ok(-moved)
+    } else {
+        Ok(moved)
+    }
+}
+
+// Formats an amount so that get_signed_amount reads it back unchanged
+pub fn format_signed_amount(amount: Money) -> String {
+    if amount > Money::default() {
+        format!("+{}", locale::format_amount(amount))
+    } else {
+        locale::format_amount(amount)
+    }
 }
 
+// Parses a repeat whose changed amounts are given the same way as the amount of the entry
+fn get_signed_repeat(repeat: &str, amount: Money) -> Result<Recurrence, LedgerError> {
+    let mut repeat = get_repeat_from_input(repeat)?;
+    if amount < Money::default() {
+        for exception in repeat.exceptions.iter_mut() {
+            if let Exception::Change(_, amount) = exception {
+                *amount = -*amount;
+            }
+        }
+    }
+    Ok(repeat)
+}
+
+// Adds an entry, further properties like the category are taken from the given template
 pub fn add_date_entry(
     poss_date: &Option<String>,
-    amount: f64,
+    amount: &str,
+    income: bool,
     description: &str,
     repeat: &Option<String>,
-) -> Result<(), Box<dyn Error>> {
+    template: Transaction,
+) -> Result<(), LedgerError> {
     let date = get_date_or_today(poss_date)?;
-    add_entry(
-        date.year() as u32,
-        date.month(),
-        date.day(),
-        -amount,
-        description,
-        match repeat {
-            Some(v) => &v[..],
-            None => "",
-        }
-    )
+    let amount = get_signed_amount(amount, income)?;
+    let repeat = get_signed_repeat(repeat.as_deref().unwrap_or_default(), amount)?;
+    // the amount could not be shown in the base currency otherwise
+    ExchangeRates::load()?
+        .get_rate(&template.currency, date)
+        .map_err(|e| format!("{}, set it with rate first", e))?;
+    add_transaction(Transaction {
+        date,
+        amount,
+        description: description.to_string(),
+        repeat,
+        ..template
+    })
+}
+
+// Writes all occurrences of repeating transactions up to the given month ("YYYY-MM")
+// into the month files
+pub fn generate_entries(until: &str) -> Result<(), LedgerError> {
+    let until = get_month_or_current(&Some(until.to_string()))?;
+    let until = (until.year() as u32, until.month());
+    let first = match get_year_months()?.first() {
+        Some(first) => *first,
+        None => return Ok(()),
+    };
+    let mut storage = storage::open()?;
+    let occurrences = get_occurrences_between(&storage.load_all()?, first, until);
+    let amount_generated = occurrences.len();
+    for mut occurrence in occurrences {
+        occurrence.repeat = Recurrence::default();
+        storage.insert(occurrence)?;
+    }
+    println!("Generated {} entries", amount_generated);
+    Ok(())
 }
 
 pub fn print_date_list(
     poss_date: &Option<String>,
     is_detailed: bool,
-) -> Result<(), Box<dyn Error>> {
+    filter: &Filter,
+) -> Result<(), LedgerError> {
     let date = get_date_or_today(poss_date)?;
     println!("------------------------------------------------------------");
-    print_list(date.year() as u32, date.month())?;
+    print_list(date.year() as u32, date.month(), filter)?;
     println!("------------------------------------------------------------");
+    warn_missing_rate(&date)?;
     if is_detailed {
-        print_sum_for_month(date.year() as u32, date.month())?;
+        print_sum_for_month(date.year() as u32, date.month(), filter)?;
+        budget::print_budget_status(&get_budget_status_for_month(&date)?);
+    }
+    Ok(())
+}
+
+// Prints the entries of all months that are marked as reimbursable
+pub fn print_reimbursable() -> Result<(), LedgerError> {
+    let filter = Filter {
+        switches: vec![REIMBURSABLE.to_string()],
+        ..Filter::default()
+    };
+    let rates = ExchangeRates::load()?;
+    let entries: Vec<(usize, Transaction)> = with_month_indices(storage::open()?.load_all()?)
+        .into_iter()
+        .filter(|(_, x)| filter.matches(x))
+        .collect();
+    let mut sum = Money::default();
+    println!("------------------------------------------------------------");
+    for (index, transaction) in &entries {
+        println!(
+            "{} {:>3}  {}",
+            transaction.date.format("%Y-%m"),
+            index,
+            transaction
+        );
+        sum += rates.convert(transaction);
+    }
+    println!("------------------------------------------------------------");
+    if let Some(warning) = rates.get_missing_rate(entries.iter().map(|(_, x)| x)) {
+        eprintln!("{}", warning);
+    }
+    println!(
+        "Open:\t\t\t{:>7}",
+        Locale::current().format_currency(sum, rates.base())
+    );
+    Ok(())
+}
+
+// Flips the sign of the entries in the given months ("YYYY-MM") that were entered
+// with the wrong sign, transfers are left alone since their signs are always right
+pub fn flip_signs(
+    from: &str,
+    poss_until: &Option<String>,
+    only_positive: bool,
+    filter: &Filter,
+    dry_run: bool,
+) -> Result<(), LedgerError> {
+    let from = get_month_or_current(&Some(from.to_string()))?;
+    let until = match poss_until {
+        Some(_) => get_month_or_current(poss_until)?,
+        None => from,
+    };
+    let (next_year, next_month) = get_next_month(until.year() as u32, until.month());
+    let until = NaiveDate::from_ymd(next_year as i32, next_month, 1).pred();
+    let mut storage = storage::open()?;
+    let mut amount_flipped = 0;
+    println!("------------------------------------------------------------");
+    for (index, mut transaction) in with_month_indices(storage.load(from, until)?) {
+        if transaction.is_transfer()
+            || !filter.matches(&transaction)
+            || (only_positive && transaction.amount <= Money::default())
+        {
+            continue;
+        }
+        transaction.amount = -transaction.amount;
+        for exception in transaction.repeat.exceptions.iter_mut() {
+            if let Exception::Change(_, amount) = exception {
+                *amount = -*amount;
+            }
+        }
+        println!(
+            "{} {:>3}  {}",
+            transaction.date.format("%Y-%m"),
+            index,
+            transaction
+        );
+        amount_flipped += 1;
+        if !dry_run {
+            storage.update(transaction)?;
+        }
+    }
+    println!("------------------------------------------------------------");
+    if dry_run {
+        println!("Would flip {} entries", amount_flipped);
+    } else {
+        println!("Flipped {} entries", amount_flipped);
     }
     Ok(())
 }
 
+// Prints the sums per category and subcategory of a month ("YYYY-MM")
+pub fn print_category_report(poss_month: &Option<String>) -> Result<(), LedgerError> {
+    let date = get_month_or_current(poss_month)?;
+    let transactions = get_income_and_expenses(date.year() as u32, date.month())?;
+    let sums = category::get_category_sums(&transactions);
+    println!("------------------------------------------------------------");
+    category::print_category_sums(&sums);
+    println!("------------------------------------------------------------");
+    let sum: Money = transactions.iter().map(|x| x.amount).sum();
+    println!(
+        "{:<30}\t{:>7}",
+        "Sum",
+        Locale::current().format_currency(sum, &get_base_currency())
+    );
+    warn_missing_rate(&date)
+}
+
+pub fn get_budget_status_for_month(date: &NaiveDate) -> Result<Vec<BudgetStatus>, LedgerError> {
+    let transactions = get_income_and_expenses(date.year() as u32, date.month())?;
+    Ok(budget::get_budget_status(&category::get_category_sums(
+        &transactions,
+    ))?)
+}
+
+// Prints budget, spending and remaining amount per category of a month ("YYYY-MM")
+pub fn print_budgets(poss_month: &Option<String>) -> Result<(), LedgerError> {
+    let date = get_month_or_current(poss_month)?;
+    let status = get_budget_status_for_month(&date)?;
+    if status.is_empty() {
+        println!("No budgets set");
+    }
+    budget::print_budget_status(&status);
+    warn_missing_rate(&date)
+}
+
+// Gets the balance of every account at the end of the given month
+pub fn get_balances(date: &NaiveDate) -> Result<Balances, LedgerError> {
+    Ok(get_month_balances(date.year() as u32, date.month())?.1)
+}
+
+// Gets the balance of every account before and at the end of the given month, starting with
+// the opening balances, all entries are loaded once for both
+pub fn get_month_balances(year: u32, month: u32) -> Result<(Balances, Balances), LedgerError> {
+    let first_day = NaiveDate::from_ymd(year as i32, month, 1);
+    let (next_year, next_month) = get_next_month(year, month);
+    let next_first_day = NaiveDate::from_ymd(next_year as i32, next_month, 1);
+    let entries = load_until_month(&*storage::open()?, NaiveDate::MIN, year, month)?;
+    let mut transactions: Vec<Transaction> = entries
+        .iter()
+        .filter(|x| x.date < next_first_day)
+        .cloned()
+        .collect();
+    if let Some(first) = entries.iter().map(|x| x.date).min() {
+        transactions.extend(get_occurrences_between(
+            &entries,
+            (first.year() as u32, first.month()),
+            (year, month),
+        ));
+    }
+    let mut before = account::get_opening_balances()?;
+    let mut after = before.clone();
+    for transaction in ExchangeRates::load()?.to_base(transactions) {
+        let account = transaction.account().to_string();
+        if transaction.date < first_day {
+            *before.entry(account.clone()).or_default() += transaction.amount;
+        }
+        *after.entry(account).or_default() += transaction.amount;
+    }
+    Ok((before, after))
+}
+
+// Gets the balance of an account (or of all accounts) before the given month
+pub fn get_balance_before(
+    year: u32,
+    month: u32,
+    account: Option<&str>,
+) -> Result<Money, LedgerError> {
+    let balances = get_month_balances(year, month)?.0;
+    Ok(match account {
+        Some(account) => balances.get(account).copied().unwrap_or_default(),
+        None => balances.values().sum(),
+    })
+}
+
+// Prints the balance of every account at the end of a month ("YYYY-MM")
+pub fn print_balances(poss_month: &Option<String>) -> Result<(), LedgerError> {
+    let date = get_month_or_current(poss_month)?;
+    let balances = get_balances(&date)?;
+    let rates = ExchangeRates::load()?;
+    println!("------------------------------------------------------------");
+    for (account, balance) in &balances {
+        println!("{:<30}\t{:>7}", account, locale::format_amount(*balance));
+    }
+    println!("------------------------------------------------------------");
+    println!(
+        "{:<30}\t{:>7}",
+        "Sum",
+        Locale::current().format_currency(balances.values().sum::<Money>(), rates.base())
+    );
+    let (next_year, next_month) = get_next_month(date.year() as u32, date.month());
+    let entries = storage::open()?.load(
+        NaiveDate::MIN,
+        NaiveDate::from_ymd(next_year as i32, next_month, 1).pred(),
+    )?;
+    if let Some(warning) = rates.get_missing_rate(&entries) {
+        eprintln!("{}", warning);
+    }
+    Ok(())
+}
+
+// Gets the names of all accounts that have entries or an opening balance
+pub fn get_accounts() -> Result<Vec<String>, LedgerError> {
+    let mut accounts: BTreeSet<String> = account::get_opening_balances()?.into_keys().collect();
+    for transaction in storage::open()?.load_all()? {
+        accounts.insert(transaction.account().to_string());
+    }
+    Ok(accounts.into_iter().collect())
+}
+
 pub fn get_transactions_for_month(
     poss_date: &Option<String>,
-) -> Result<Vec<Transaction>, Box<dyn Error>> {
+) -> Result<Vec<Transaction>, LedgerError> {
     let date = get_date_or_today(poss_date)?;
-    let filename = get_filename_from_date(date.year() as u32, date.month())?;
-    let mut transactions = get_transactions(&filename)?;
-    transactions.sort();
-    Ok(transactions)
+    Ok(storage::open()?.load_month(date.year() as u32, date.month())?)
+}
+
+// Version of the stored entries of a month, see Storage::version
+pub fn get_month_version(date: &NaiveDate) -> Result<u64, LedgerError> {
+    Ok(storage::open()?.version(date.year() as u32, date.month())?)
+}
+
+pub fn get_occurrences_for_month(
+    poss_date: &Option<String>,
+) -> Result<Vec<Transaction>, LedgerError> {
+    let date = get_date_or_today(poss_date)?;
+    get_occurrences(date.year() as u32, date.month())
+}
+
+// Finds a stored entry by its id or by its index in the month of the given date as shown by list
+fn find_entry(
+    storage: &dyn storage::Storage,
+    date: NaiveDate,
+    entry: &str,
+) -> Result<Transaction, LedgerError> {
+    // the month of the date is searched first, the other months only for ids
+    let mut transactions = storage.load_month(date.year() as u32, date.month())?;
+    if let Some(index) = transactions.iter().position(|x| x.id == entry) {
+        return Ok(transactions.swap_remove(index));
+    }
+    let index: usize = match entry.parse() {
+        Ok(index) if index < transactions.len() => return Ok(transactions.swap_remove(index)),
+        Ok(index) => index,
+        Err(_) => {
+            return storage
+                .get(entry)?
+                .ok_or_else(|| LedgerError::EntryNotFound(entry.to_string()))
+        }
+    };
+    // an id that only consists of digits
+    match storage.get(entry) {
+        Ok(Some(transaction)) => Ok(transaction),
+        _ => Err(LedgerError::IndexOutOfRange {
+            index,
+            amount: transactions.len(),
+        }),
+    }
 }
 
-pub fn del_entry(poss_date: &Option<String>, index: usize) -> Result<(), Box<dyn Error>> {
+pub fn del_entry(
+    poss_date: &Option<String>,
+    entry: &str,
+    filter: &Filter,
+) -> Result<(), LedgerError> {
+    let mut storage = storage::open()?;
+    let transaction = find_entry(&*storage, get_date_or_today(poss_date)?, entry)?;
+    // the filter only guards against deleting the wrong entry
+    if !filter.matches(&transaction) {
+        return Err(LedgerError::Other(format!(
+            "Entry {} belongs to account {}",
+            entry,
+            transaction.account()
+        )));
+    }
+    let removed = storage.delete(&transaction.id)?;
+    skip_generated_occurrence(&mut *storage, &removed)?;
+    del_transfer_partner(&mut *storage, &removed)
+}
+
+// Fields of an entry that are changed by edit, the others are kept
+#[derive(Default)]
+pub struct Changes {
+    pub date: Option<String>,
+    pub amount: Option<String>,
+    pub description: Option<String>,
+    pub repeat: Option<String>,
+    pub category: Option<String>,
+    pub account: Option<String>,
+}
+
+// Changes the given fields of an entry, which is moved if its date is in another month now
+pub fn edit_entry(
+    poss_month: &Option<String>,
+    entry: &str,
+    changes: &Changes,
+) -> Result<(), LedgerError> {
+    let mut storage = storage::open()?;
+    let mut transaction = find_entry(&*storage, get_month_or_current(poss_month)?, entry)?;
+    if let Some(date) = &changes.date {
+        transaction.date = get_date(date)?;
+    }
+    if let Some(amount) = &changes.amount {
+        transaction.amount = get_changed_amount(&transaction, amount)?;
+    }
+    if let Some(description) = &changes.description {
+        transaction.description = description.clone();
+    }
+    if let Some(repeat) = &changes.repeat {
+        transaction.repeat = get_signed_repeat(repeat, transaction.amount)?;
+    }
+    if let Some(category) = &changes.category {
+        transaction.category = category.trim().to_string();
+    }
+    if let Some(account) = &changes.account {
+        transaction.account = account.trim().to_string();
+    }
+    update_with_partner(&mut *storage, transaction)
+}
+
+pub fn del_entry_by_id(id: &str) -> Result<(), LedgerError> {
+    let mut storage = storage::open()?;
+    let removed = storage.delete(id)?;
+    skip_generated_occurrence(&mut *storage, &removed)?;
+    del_transfer_partner(&mut *storage, &removed)
+}
+
+// A deleted generated entry would be shown as occurrence of its template again,
+// so that occurrence is skipped in the template
+fn skip_generated_occurrence(
+    storage: &mut dyn storage::Storage,
+    transaction: &Transaction,
+) -> Result<(), LedgerError> {
+    let (id, number) = match recurrence::parse_origin(&transaction.origin) {
+        Some(origin) => origin,
+        None => return Ok(()),
+    };
+    let mut template = match storage.get(id)? {
+        Some(template) => template,
+        None => return Ok(()),
+    };
+    if let Some(date) = recurrence::get_occurrence_date(&template, number) {
+        template.repeat.exceptions.push(Exception::Skip(date));
+        storage.update(template)?;
+    }
+    Ok(())
+}
+
+// Removes the other entry of a transfer from whichever month it is in
+fn del_transfer_partner(
+    storage: &mut dyn storage::Storage,
+    transaction: &Transaction,
+) -> Result<(), LedgerError> {
+    if !transaction.is_transfer() {
+        return Ok(());
+    }
+    for partner in storage.load_all()? {
+        if partner.transfer == transaction.transfer {
+            storage.delete(&partner.id)?;
+        }
+    }
+    Ok(())
+}
+
+// Moves money between two accounts by writing a debit and a credit entry that are linked
+pub fn add_transfer(
+    date: NaiveDate,
+    amount: Money,
+    from: &str,
+    to: &str,
+    description: &str,
+) -> Result<(), LedgerError> {
+    let account_name = |account: &str| match account.is_empty() {
+        true => DEFAULT_ACCOUNT.to_string(),
+        false => account.to_string(),
+    };
+    if account_name(from) == account_name(to) {
+        return Err(LedgerError::Other(format!(
+            "A transfer needs two different accounts, both are {}",
+            account_name(from)
+        )));
+    }
+    // the direction is given by the accounts, so a negative amount would swap them
+    let amount = amount.abs();
+    let link = new_id();
+    let description = if description.is_empty() {
+        format!("Transfer from {} to {}", from, to)
+    } else {
+        description.to_string()
+    };
+    let debit = Transaction {
+        date,
+        amount: -amount,
+        description,
+        account: from.to_string(),
+        transfer: link,
+        ..Transaction::default()
+    };
+    let credit = Transaction {
+        amount,
+        account: to.to_string(),
+        ..debit.clone()
+    };
+    // both entries are added through the same storage, so that they are undone together
+    let mut storage = storage::open()?;
+    storage.insert(debit)?;
+    storage.insert(credit)?;
+    Ok(())
+}
+
+pub fn add_date_transfer(
+    poss_date: &Option<String>,
+    amount: Money,
+    from: &str,
+    to: &str,
+    description: &Option<String>,
+) -> Result<(), LedgerError> {
     let date = get_date_or_today(poss_date)?;
-    let filename = get_filename_from_date(date.year() as u32, date.month())?;
-    let mut transactions = get_transactions(&filename)?;
-    transactions.remove(index);
-    write_entries(&mut transactions, filename)
+    add_transfer(
+        date,
+        amount,
+        from,
+        to,
+        description.as_deref().unwrap_or_default(),
+    )
+}
+
+// Reverts the last change to the entries, also of an earlier session
+pub fn undo() -> Result<String, LedgerError> {
+    Ok(match storage::undo()? {
+        Some(description) => format!("Undid {}", description),
+        None => "Nothing to undo".to_string(),
+    })
 }
 
-pub fn del_entry_by_date(date: &NaiveDate, index: usize) -> Result<(), Box<dyn Error>> {
-    let filename = get_filename_from_date(date.year() as u32, date.month())?;
-    let mut transactions = get_transactions(&filename)?;
-    transactions.remove(index);
-    write_entries(&mut transactions, filename)
+// Makes the last undone change again
+pub fn redo() -> Result<String, LedgerError> {
+    Ok(match storage::redo()? {
+        Some(description) => format!("Redid {}", description),
+        None => "Nothing to redo".to_string(),
+    })
 }
 
-pub fn get_date(date: &str) -> Result<NaiveDate, chrono::ParseError> {
-    date_serializer::string_to_time(date)
+// Parses a date in the configured date format, dates like 2026-03-01 are always understood
+pub fn get_date(date: &str) -> Result<NaiveDate, LedgerError> {
+    let parsed = match &config::get().date_format {
+        Some(format) => NaiveDate::parse_from_str(date, format)
+            .or_else(|_| date_serializer::string_to_time(date)),
+        None => date_serializer::string_to_time(date),
+    };
+    parsed.map_err(|_| {
+        let example = NaiveDate::from_ymd(2026, 3, 1);
+        let expected = match config::get().date_format {
+            Some(_) => format!("a date like {} or 2026-03-01", format_date(example)),
+            None => "a date like 2026-03-01".to_string(),
+        };
+        LedgerError::InvalidDate {
+            date: date.to_string(),
+            expected,
+        }
+    })
 }
 
-pub fn get_date_or_today(poss_date: &Option<String>) -> Result<NaiveDate, chrono::ParseError> {
+// Formats a date in the configured date format
+pub fn format_date(date: NaiveDate) -> String {
+    match &config::get().date_format {
+        Some(format) => date.format(format).to_string(),
+        None => date_serializer::time_to_csv(date),
+    }
+}
+
+pub fn get_date_or_today(poss_date: &Option<String>) -> Result<NaiveDate, LedgerError> {
     match poss_date {
         None => {
             let today = chrono::offset::Local::today();
             Ok(today.naive_local())
         }
-        Some(date) => date_serializer::string_to_time(date),
+        Some(date) => get_date(date),
     }
 }
 
-pub fn get_months() -> Result<Vec<String>, Box<dyn Error>> {
-    let base_path_string = get_base_path()?;
-    let base_path = Path::new(&base_path_string);
-    let mut result = Vec::new();
-    for entry in fs::read_dir(base_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        let path_copy = path.clone();
-        let path_stem = path_copy.file_stem().unwrap().to_str().unwrap();
-        if !path.is_dir() {
-            continue;
-        }
-        for month in fs::read_dir(path)? {
-            let month = month?;
-            let month_path = month.path();
-            if month_path.is_dir() {
-                continue;
+// Gets the first day of a month given as "YYYY-MM" or of the current month
+pub fn get_month_or_current(poss_month: &Option<String>) -> Result<NaiveDate, LedgerError> {
+    let date = match poss_month {
+        None => chrono::offset::Local::today().naive_local(),
+        Some(month) => date_serializer::string_to_time(&format!("{}-01", month)).map_err(|_| {
+            LedgerError::InvalidDate {
+                date: month.clone(),
+                expected: "a month like 2026-03".to_string(),
             }
-            let text = format!(
-                "{}-{}",
-                path_stem,
-                month_path.file_stem().unwrap().to_str().unwrap()
-            );
-            result.push(text);
-        }
+        })?,
+    };
+    Ok(NaiveDate::from_ymd(date.year(), date.month(), 1))
+}
+
+pub fn get_months() -> Result<Vec<String>, LedgerError> {
+    Ok(get_year_months()?
+        .into_iter()
+        .map(|(year, month)| format!("{}-{:0>2}", year, month))
+        .collect())
+}
+
+pub fn get_next_month(year: u32, month: u32) -> (u32, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
     }
-    result.sort();
-    Ok(result)
+}
+
+// Gets all stored months as (year, month) pairs in ascending order
+fn get_year_months() -> Result<Vec<(u32, u32)>, LedgerError> {
+    Ok(storage::open()?.months()?)
+}
+
+// Pairs entries sorted by date with their index in their month, as shown by list
+fn with_month_indices(transactions: Vec<Transaction>) -> Vec<(usize, Transaction)> {
+    let mut index = 0;
+    let mut previous = None;
+    transactions
+        .into_iter()
+        .map(|transaction| {
+            let month = (transaction.date.year(), transaction.date.month());
+            index = if previous == Some(month) {
+                index + 1
+            } else {
+                0
+            };
+            previous = Some(month);
+            (index, transaction)
+        })
+        .collect()
 }
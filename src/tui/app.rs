@@ -1,7 +1,12 @@
+use crate::budget::BudgetStatus;
+use crate::config;
+use crate::error::LedgerError;
+use crate::exchange::ExchangeRates;
+use crate::money::Money;
+use std::collections::BTreeMap;
 use crate::transaction::{self, Transaction};
 use std::fmt;
-use std::error::Error;
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use tui::widgets::{TableState, ListState};
 
 // Possible Actions one can partake
@@ -9,6 +14,8 @@ pub enum ActionState {
     Normal,
     Add(AddState, Transaction),
     Update(UpdateState, Transaction),
+    // the transaction is the debit entry, the string the account the money goes to
+    Transfer(TransferState, Transaction, String),
 }
 
 // Phases when adding a new entry
@@ -17,13 +24,18 @@ pub enum AddState {
     Date,
     Amount,
     Description,
+    Category,
+    Account,
+    Tags,
 }
 
 impl fmt::Display for AddState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{:?}", self)
-        // or, alternatively:
-        // fmt::Debug::fmt(self, f)
+        match self {
+            // amounts are expenses unless they are marked as income
+            AddState::Amount => write!(f, "Amount (+ for income)"),
+            _ => write!(f, "{:?}", self),
+        }
     }
 }
 
@@ -33,22 +45,55 @@ pub enum UpdateState {
     Date,
     Amount,
     Description,
+    Category,
+    Account,
+    Tags,
 }
 
 impl fmt::Display for UpdateState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // amounts are expenses unless they are marked as income
+            UpdateState::Amount => write!(f, "Amount (+ for income)"),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+// Phases when moving money between accounts
+#[derive(Debug, Clone, Copy)]
+pub enum TransferState {
+    Date,
+    Amount,
+    From,
+    To,
+    Description,
+}
+
+impl fmt::Display for TransferState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
-        // or, alternatively:
-        // fmt::Debug::fmt(self, f)
     }
 }
 
+// Entry of the account list that shows the entries of all accounts
+pub const ALL_ACCOUNTS: &str = "All";
+
 pub struct App {
     pub months: Vec<String>,
     pub current_month: NaiveDate,
     pub month_state: ListState,
+    pub accounts: Vec<String>,
+    pub account_state: ListState,
+    pub balances: BTreeMap<String, Money>,
+    pub balances_before: BTreeMap<String, Money>,
     pub transaction_state: TableState,
     pub transactions: Vec<Transaction>,
+    // version of the stored entries of the month when they were loaded
+    pub version: u64,
+    pub occurrences: Vec<Transaction>,
+    pub budgets: Vec<BudgetStatus>,
+    pub rates: ExchangeRates,
     pub input: String,
     pub state: ActionState,
 }
@@ -57,26 +102,246 @@ impl App {
     pub fn refresh_transactions(&mut self) {
         self.refresh_current_month();
         self.transactions =
-            get_transactions_for_selected_month(&self.month_state).expect("can get transactions");
+            match transaction::get_transactions_for_month(&Some(self.current_month.to_string())) {
+                Ok(transactions) => transactions,
+                Err(e) => {
+                    self.input = format!("Cannot read entries: {}", e);
+                    Vec::new()
+                }
+            };
+        self.version = transaction::get_month_version(&self.current_month).unwrap_or_default();
+        self.occurrences =
+            transaction::get_occurrences_for_month(&Some(self.current_month.to_string()))
+                .unwrap_or_default();
+        self.budgets =
+            transaction::get_budget_status_for_month(&self.current_month).unwrap_or_default();
+        self.refresh_accounts();
+    }
+
+    // Reloads the accounts and their balances, keeping the selected account
+    pub fn refresh_accounts(&mut self) {
+        self.rates = ExchangeRates::load().unwrap_or_default();
+        let selected = self.selected_account().map(|x| x.to_string());
+        self.accounts = vec![ALL_ACCOUNTS.to_string()];
+        self.accounts
+            .extend(transaction::get_accounts().unwrap_or_default());
+        let index = selected
+            .and_then(|selected| self.accounts.iter().position(|x| *x == selected))
+            .unwrap_or(0);
+        self.account_state.select(Some(index));
+        (self.balances_before, self.balances) = transaction::get_month_balances(
+            self.current_month.year() as u32,
+            self.current_month.month(),
+        )
+        .unwrap_or_default();
+    }
+
+    // Gets the selected account, None if all accounts are shown
+    pub fn selected_account(&self) -> Option<&str> {
+        match self.account_state.selected() {
+            Some(index) if index > 0 => self.accounts.get(index).map(|x| &x[..]),
+            _ => None,
+        }
+    }
+
+    // Account that new entries get unless it is changed, the selected account or the
+    // configured default account
+    pub fn get_new_entry_account(&self) -> String {
+        self.selected_account()
+            .map(str::to_string)
+            .or_else(|| config::get().account.clone())
+            .unwrap_or_default()
+    }
+
+    // Shows the next account, after the last one all accounts are shown again
+    pub fn next_account(&mut self) {
+        let selected = self.account_state.selected().unwrap_or(0);
+        self.account_state
+            .select(Some((selected + 1) % self.accounts.len().max(1)));
+        self.transaction_state.select(Some(0));
     }
 
+    fn is_visible(&self, transaction: &Transaction) -> bool {
+        self.selected_account()
+            .is_none_or(|account| transaction.account() == account)
+    }
+
+    // Indices of the entries of the selected account in the entries of the month
+    pub fn visible_transactions(&self) -> Vec<usize> {
+        (0..self.transactions.len())
+            .filter(|index| self.is_visible(&self.transactions[*index]))
+            .collect()
+    }
+
+    pub fn visible_occurrences(&self) -> Vec<&Transaction> {
+        self.occurrences
+            .iter()
+            .filter(|x| self.is_visible(x))
+            .collect()
+    }
+
+    // Balances after each visible entry and after each visible occurrence, counted in date order
+    pub fn running_balances(&self) -> (Vec<Money>, Vec<Money>) {
+        let account = self.selected_account();
+        let start = match account {
+            Some(account) => self
+                .balances_before
+                .get(account)
+                .copied()
+                .unwrap_or_default(),
+            None => self.balances_before.values().sum(),
+        };
+        let visible = self.visible_transactions();
+        let mut entries: Vec<(usize, &Transaction)> = visible
+            .iter()
+            .map(|index| &self.transactions[*index])
+            .chain(self.visible_occurrences())
+            .enumerate()
+            .collect();
+        entries.sort_by(|a, b| a.1.cmp(b.1));
+        let converted: Vec<Transaction> = entries
+            .iter()
+            .map(|(_, transaction)| Transaction {
+                amount: self.amount_in_base(transaction),
+                ..(*transaction).clone()
+            })
+            .collect();
+        let balances = transaction::get_running_balances(start, &converted, account);
+        let mut ordered = vec![Money::default(); entries.len()];
+        for ((position, _), balance) in entries.iter().zip(balances) {
+            ordered[*position] = balance;
+        }
+        let occurrence_balances = ordered.split_off(visible.len());
+        (ordered, occurrence_balances)
+    }
+
+    // Amount in the base currency, the original amount if there is no exchange rate
+    // like in list, set_input_to_sum shows the warning about it
+    pub fn amount_in_base(&self, transaction: &Transaction) -> Money {
+        self.rates.convert(transaction)
+    }
+
+    // Index of the selected entry in the entries of the month
+    pub fn selected_index(&self) -> Option<usize> {
+        self.transaction_state
+            .selected()
+            .and_then(|selected| self.visible_transactions().get(selected).copied())
+    }
+
+    // Reloads the months, keeping the selected month even if months were added before it
     pub fn refresh_months(&mut self) {
+        let selected = self
+            .month_state
+            .selected()
+            .and_then(|index| self.months.get(index))
+            .cloned();
         self.months = transaction::get_months().unwrap_or_default();
+        let index = selected
+            .and_then(|selected| self.months.iter().position(|x| *x == selected))
+            .or(self.months.len().checked_sub(1));
+        self.month_state.select(index);
+    }
+
+    // Shows the month of a stored entry and selects the entry
+    pub fn select_entry(&mut self, transaction: &Transaction) {
+        self.refresh_months();
+        let month = transaction.date.format("%Y-%m").to_string();
+        if let Some(index) = self.months.iter().position(|x| *x == month) {
+            self.month_state.select(Some(index));
+        }
+        self.refresh_transactions();
+        let row = self
+            .visible_transactions()
+            .iter()
+            .position(|index| self.transactions[*index].id == transaction.id);
+        self.transaction_state.select(Some(row.unwrap_or(0)));
     }
 
     pub fn set_input_to_sum(&mut self) {
-        if let Ok(sum) = transaction::get_formatted_sum_for_month(&self.current_month) {
-            self.input = format!("Sum for current month: {}", sum);
+        if self.months.is_empty() {
+            self.input = format!(
+                "There are no entries yet, press '{}' to add the first one",
+                config::get().keys.add
+            );
         } else {
-            self.input = String::new();
+            self.input = match transaction::get_formatted_sum_for_month(&self.current_month) {
+                Ok(sum) => format!("Sum for current month: {}", sum),
+                Err(e) => format!("Cannot read entries: {}", e),
+            };
+            if let Ok(Some(warning)) = transaction::get_missing_rate_for_month(&self.current_month)
+            {
+                self.input += &format!(" ({})", warning);
+            }
+        }
+    }
+
+    // Checks that the entries of the month were not changed by another program since they
+    // were loaded, otherwise they are reloaded instead of overwriting the other changes
+    pub fn is_unchanged(&mut self) -> bool {
+        let version = transaction::get_month_version(&self.current_month).ok();
+        if version == Some(self.version) {
+            return true;
         }
+        self.input = "Entries were changed by another program, they are reloaded".to_string();
+        self.refresh_transactions();
+        false
     }
 
+    // Turns a switch of the selected entry on or off and saves the month
+    pub fn toggle_switch(&mut self, switch: &str) {
+        if !self.is_unchanged() {
+            return;
+        }
+        let selected = match self.selected_index() {
+            Some(selected) => selected,
+            None => {
+                self.input = "No entry is selected".to_string();
+                return;
+            }
+        };
+        let mut transaction = self.transactions[selected].clone();
+        if !transaction.switches.remove(switch) {
+            transaction.switches.insert(switch.to_string());
+        }
+        match transaction::update_transaction(transaction) {
+            Ok(_) => self.input = format!("Toggled {}", switch),
+            Err(e) => self.input = format!("Cannot save entry: {}", e),
+        }
+        self.refresh_transactions();
+    }
+
+    // Reverts the last change to the entries, which may have been made by another session
+    pub fn undo(&mut self) {
+        let result = transaction::undo();
+        self.show_history_result(result);
+    }
+
+    // Makes the last undone change again
+    pub fn redo(&mut self) {
+        let result = transaction::redo();
+        self.show_history_result(result);
+    }
+
+    fn show_history_result(&mut self, result: Result<String, LedgerError>) {
+        self.input = match result {
+            Ok(message) => message,
+            Err(e) => format!("Cannot undo or redo: {}", e),
+        };
+        self.refresh_months();
+        self.refresh_transactions();
+    }
+
+    // The selected month, the current month as long as there are no entries
     fn refresh_current_month(&mut self) {
-        let month_without_day =
-            &self.months[self.month_state.selected().expect("something is selected")];
-        let month_with_day = format!("{}-01", month_without_day);
-        self.current_month = transaction::get_date(&month_with_day).expect("months are correct");
+        let selected = self
+            .month_state
+            .selected()
+            .and_then(|index| self.months.get(index))
+            .cloned();
+        // a month that cannot be read keeps the month that was shown before
+        if let Ok(month) = transaction::get_month_or_current(&selected) {
+            self.current_month = month;
+        }
     }
 }
 
@@ -86,38 +351,23 @@ impl Default for App {
             months: transaction::get_months().unwrap_or_default(),
             current_month: NaiveDate::default(),
             transactions: Vec::new(),
+            version: 0,
+            occurrences: Vec::new(),
+            budgets: Vec::new(),
+            rates: ExchangeRates::default(),
             input: String::new(),
             month_state: ListState::default(),
+            accounts: Vec::new(),
+            account_state: ListState::default(),
+            balances: BTreeMap::new(),
+            balances_before: BTreeMap::new(),
             transaction_state: TableState::default(),
             state: ActionState::Normal,
         };
-        app.month_state.select(Some(app.months.len() - 1));
-        app.refresh_current_month();
+        // an empty ledger has no month to select
+        app.month_state.select(app.months.len().checked_sub(1));
         app.transaction_state.select(Some(0));
-        app.transactions =
-            get_transactions_for_selected_month(&app.month_state).unwrap_or_default();
+        app.refresh_transactions();
         app
     }
 }
-
-// gets the selected month
-fn get_selected_month(month_list_state: &ListState) -> Result<String, Box<dyn Error>> {
-    let month_list = transaction::get_months()?;
-    let selected_month = month_list
-        .get(
-            month_list_state
-                .selected()
-                .expect("there is always a selected month"),
-        )
-        .expect("exists")
-        .clone();
-    Ok(format!("{}-01", selected_month))
-}
-
-fn get_transactions_for_selected_month(
-    month_list_state: &ListState,
-) -> Result<Vec<Transaction>, Box<dyn Error>> {
-    let month = get_selected_month(month_list_state)?;
-    let poss_month = Some(month);
-    transaction::get_transactions_for_month(&poss_month)
-}
@@ -1,9 +1,11 @@
+use crate::locale;
+use crate::set_serializer;
 use crate::transaction::{self, Transaction};
-use crate::tui::app::{App, ActionState, AddState, UpdateState};
+use crate::tui::app::{App, ActionState, AddState, TransferState, UpdateState};
 
 pub fn add_enter(app: &mut App) {
-    match app.state {
-        ActionState::Add(ref mut state, ref mut transaction) => match state {
+    if let ActionState::Add(ref mut state, ref mut transaction) = app.state {
+        match state {
             AddState::Date => {
                 let poss_date = match app.input.is_empty() {
                     true => None,
@@ -16,58 +18,157 @@ pub fn add_enter(app: &mut App) {
                 app.input = String::new();
             }
             AddState::Amount => {
-                *state = AddState::Description;
-                let amount: f64 = app.input.parse().expect("can get amount");
-                transaction.amount = amount;
+                if let Ok(amount) = transaction::get_changed_amount(transaction, &app.input) {
+                    transaction.amount = amount;
+                    *state = AddState::Description;
+                }
                 app.input = String::new();
             }
             AddState::Description => {
-                *state = AddState::Date;
+                *state = AddState::Category;
                 transaction.description = app.input.clone();
-                transaction::add_transaction(transaction.clone()).expect("can write transaction");
+                app.input = String::new();
+            }
+            AddState::Category => {
+                *state = AddState::Account;
+                transaction.category = app.input.trim().to_string();
+                app.input = app.get_new_entry_account();
+            }
+            AddState::Account => {
+                *state = AddState::Tags;
+                transaction.account = app.input.trim().to_string();
+                app.input = String::new();
+            }
+            AddState::Tags => {
+                *state = AddState::Date;
+                transaction.tags = set_serializer::string_to_set(&app.input);
+                let result = transaction::add_transaction(transaction.clone());
                 *transaction = Transaction::default();
                 app.state = ActionState::Normal;
-                app.input = "Added entry successfully".to_string();
+                app.input = match result {
+                    Ok(_) => "Added entry successfully".to_string(),
+                    Err(e) => format!("Cannot save entry: {}", e),
+                };
                 app.refresh_months();
                 app.refresh_transactions();
             }
-        },
-        _ => {}
+        }
     }
 }
 
 pub fn update_enter(app: &mut App) {
-    match app.state {
-        ActionState::Update(ref mut state, ref mut transaction) => match state {
+    if let ActionState::Update(ref mut state, ref mut transaction) = app.state {
+        match state {
             UpdateState::Date => {
-                *state = UpdateState::Amount;
                 let poss_date = match app.input.is_empty() {
                     true => None,
                     false => Some(app.input.clone()),
                 };
-                transaction.date =
-                    transaction::get_date_or_today(&poss_date).expect("can get date");
-                app.input = transaction.amount.to_string();
+                if let Ok(date) = transaction::get_date_or_today(&poss_date) {
+                    transaction.date = date;
+                    *state = UpdateState::Amount;
+                    app.input = transaction::format_signed_amount(transaction.amount);
+                } else {
+                    app.input = transaction::format_date(transaction.date);
+                }
             }
             UpdateState::Amount => {
-                *state = UpdateState::Description;
-                let amount: f64 = app.input.parse().expect("can get amount");
-                transaction.amount = amount;
-                app.input = transaction.description.to_string();
+                if let Ok(amount) = transaction::get_changed_amount(transaction, &app.input) {
+                    transaction.amount = amount;
+                    *state = UpdateState::Description;
+                    app.input = transaction.description.to_string();
+                } else {
+                    app.input = transaction::format_signed_amount(transaction.amount);
+                }
             }
             UpdateState::Description => {
-                *state = UpdateState::Date;
+                *state = UpdateState::Category;
                 transaction.description = app.input.clone();
-                app.transactions[app.transaction_state.selected().expect("can get selected")] =
-                    transaction.clone();
-                transaction::write_transactions(&mut app.transactions).expect("can write");
+                app.input = transaction.category.clone();
+            }
+            UpdateState::Category => {
+                *state = UpdateState::Account;
+                transaction.category = app.input.trim().to_string();
+                app.input = transaction.account.clone();
+            }
+            UpdateState::Account => {
+                *state = UpdateState::Tags;
+                transaction.account = app.input.trim().to_string();
+                app.input = set_serializer::set_to_string(&transaction.tags);
+            }
+            UpdateState::Tags => {
+                *state = UpdateState::Date;
+                transaction.tags = set_serializer::string_to_set(&app.input);
+                let updated = transaction.clone();
+                app.state = ActionState::Normal;
+                if !app.is_unchanged() {
+                    return;
+                }
+                match transaction::update_transaction(updated.clone()) {
+                    Ok(_) => {
+                        app.input = "Updated entry successfully".to_string();
+                        // the entry may be in another month now, which is then shown
+                        app.select_entry(&updated);
+                    }
+                    Err(e) => {
+                        app.input = format!("Cannot save entry: {}", e);
+                        app.refresh_transactions();
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn transfer_enter(app: &mut App) {
+    if let ActionState::Transfer(ref mut state, ref mut transaction, ref mut to) = app.state {
+        match state {
+            TransferState::Date => {
+                let poss_date = match app.input.is_empty() {
+                    true => None,
+                    false => Some(app.input.clone()),
+                };
+                if let Ok(date) = transaction::get_date_or_today(&poss_date) {
+                    transaction.date = date;
+                    *state = TransferState::Amount;
+                }
+                app.input = String::new();
+            }
+            TransferState::Amount => {
+                if let Ok(amount) = locale::parse_amount(&app.input) {
+                    transaction.amount = amount;
+                    *state = TransferState::From;
+                    app.input = app.selected_account().unwrap_or_default().to_string();
+                } else {
+                    app.input = String::new();
+                }
+            }
+            TransferState::From => {
+                *state = TransferState::To;
+                transaction.account = app.input.trim().to_string();
+                app.input = String::new();
+            }
+            TransferState::To => {
+                *state = TransferState::Description;
+                *to = app.input.trim().to_string();
+                app.input = String::new();
+            }
+            TransferState::Description => {
+                let result = transaction::add_transfer(
+                    transaction.date,
+                    transaction.amount,
+                    &transaction.account,
+                    to,
+                    &app.input,
+                );
                 app.state = ActionState::Normal;
-                app.input = "Updated entry successfully".to_string();
+                app.input = match result {
+                    Ok(_) => "Added transfer successfully".to_string(),
+                    Err(e) => format!("Cannot write transfer: {}", e),
+                };
                 app.refresh_months();
                 app.refresh_transactions();
             }
-        },
-        _ => {}
+        }
     }
 }
-
@@ -1,9 +1,8 @@
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::error::Error;
 use std::io;
 use tui::{
     backend::{Backend, CrosstermBackend},
@@ -20,11 +19,15 @@ use unicode_width::UnicodeWidthStr;
 mod app;
 mod input_actions;
 
-use app::{App, ActionState, AddState, UpdateState};
+use app::{App, ActionState, AddState, TransferState, UpdateState};
 
+use crate::config;
+use crate::error::LedgerError;
+use crate::locale::{self, Locale};
+use crate::set_serializer;
 use crate::transaction::{self, Transaction};
 
-pub fn show_tui() -> Result<(), Box<dyn Error>> {
+pub fn show_tui() -> Result<(), LedgerError> {
     match show_tui_with_io_error() {
         Ok(_) => Ok(()),
         Err(r) => Err(r.into()),
@@ -49,14 +52,11 @@ fn show_tui_with_io_error() -> Result<(), io::Error> {
     )?;
     terminal.show_cursor()?;
 
-    if let Err(r) = res {
-        eprintln!("{:?}", r);
-    }
-
-    Ok(())
+    res
 }
 
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+    let keys = &config::get().keys;
     app.set_input_to_sum();
     loop {
         terminal.draw(|f| ui(f, &mut app))?;
@@ -64,8 +64,8 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
         if let Event::Key(key) = event::read()? {
             match app.state {
                 ActionState::Normal => match key.code {
-                    KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Char('n') => {
+                    KeyCode::Char(c) if c == keys.quit => return Ok(()),
+                    KeyCode::Char(c) if c == keys.next_month => {
                         if let Some(selected) = app.month_state.selected() {
                             let amount_months = app.months.len();
                             if selected >= amount_months - 1 {
@@ -78,7 +78,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                             app.set_input_to_sum();
                         }
                     }
-                    KeyCode::Char('p') => {
+                    KeyCode::Char(c) if c == keys.previous_month => {
                         if let Some(selected) = app.month_state.selected() {
                             let amount_months = app.months.len();
                             if selected > 0 {
@@ -91,65 +91,96 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                             app.set_input_to_sum();
                         }
                     }
-                    KeyCode::Char('j') => {
+                    KeyCode::Char(c) if c == keys.down => {
                         if let Some(selected) = app.transaction_state.selected() {
-                            let amount_transactions = app.transactions.len();
-                            if selected >= amount_transactions - 1 {
+                            let amount_transactions = app.visible_transactions().len();
+                            if selected + 1 >= amount_transactions {
                                 app.transaction_state.select(Some(0))
                             } else {
                                 app.transaction_state.select(Some(selected + 1))
                             }
                         }
                     }
-                    KeyCode::Char('k') => {
+                    KeyCode::Char(c) if c == keys.up => {
                         if let Some(selected) = app.transaction_state.selected() {
-                            let amount_transactions = app.transactions.len();
+                            let amount_transactions = app.visible_transactions().len();
                             if selected > 0 {
                                 app.transaction_state.select(Some(selected - 1))
                             } else {
-                                app.transaction_state.select(Some(amount_transactions - 1))
+                                app.transaction_state
+                                    .select(Some(amount_transactions.saturating_sub(1)))
                             }
                         }
                     }
-                    KeyCode::Char('d') => {
-                            // expect is okay, since error only happens when files are out of sync
-                            // with application
-                        if let Some(selected) = app.transaction_state.selected() {
-                            let amount_transactions = app.transactions.len();
-                            let result = transaction::del_entry_by_date(&app.current_month, selected);
+                    KeyCode::Tab => {
+                        app.next_account();
+                        app.set_input_to_sum();
+                    }
+                    KeyCode::Char(c) if c == keys.delete => {
+                        if !app.is_unchanged() {
+                            continue;
+                        }
+                        if let (Some(selected), Some(index)) =
+                            (app.transaction_state.selected(), app.selected_index())
+                        {
+                            let amount_transactions = app.visible_transactions().len();
+                            let result =
+                                transaction::del_entry_by_id(&app.transactions[index].id);
                             match result {
                                 Ok(_) => {
-                                    if amount_transactions > 1 {
-                                        if selected == amount_transactions - 1 {
-                                            app.transaction_state.select(Some(selected - 1))
-                                        }
+                                    if amount_transactions > 1
+                                        && selected == amount_transactions - 1
+                                    {
+                                        app.transaction_state.select(Some(selected - 1))
                                     }
                                     app.refresh_transactions();
                                 }
-                                Err(_) => {
-                                    app.input = "Cannot delete entry".to_string();
+                                Err(e) => {
+                                    app.input = format!("Cannot delete entry: {}", e);
                                 }
                             }
                         } else {
                             app.input = "No entry to delete is selected".to_string();
                         }
                     }
-                    KeyCode::Char('a') => {
+                    KeyCode::Char(c) if c == keys.reimbursable => {
+                        app.toggle_switch(transaction::REIMBURSABLE)
+                    }
+                    KeyCode::Char(c) if c == keys.pending => {
+                        app.toggle_switch(transaction::PENDING)
+                    }
+                    KeyCode::Char(c) if c == keys.cash => app.toggle_switch(transaction::CASH),
+                    KeyCode::Char(c) if c == keys.add => {
                         app.state = ActionState::Add(AddState::Date, Transaction::default());
                         app.input = "".to_string();
                     }
-                    KeyCode::Char('u') => {
-                        let transaction = app.transactions[app
-                            .transaction_state
-                            .selected()
-                            .expect("there is smth. selected")]
-                        .clone();
-                        app.input = transaction.date.to_string();
+                    KeyCode::Char(c) if c == keys.update => {
+                        let transaction = match app.selected_index() {
+                            Some(index) => app.transactions[index].clone(),
+                            None => continue,
+                        };
+                        app.input = transaction::format_date(transaction.date);
                         app.state = ActionState::Update(UpdateState::Date, transaction);
                     }
+                    KeyCode::Char(c) if c == keys.undo => app.undo(),
+                    KeyCode::Char(c)
+                        if c == keys.redo && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                    {
+                        app.redo()
+                    }
+                    KeyCode::Char(c) if c == keys.transfer => {
+                        app.state = ActionState::Transfer(
+                            TransferState::Date,
+                            Transaction::default(),
+                            String::new(),
+                        );
+                        app.input = "".to_string();
+                    }
                     _ => {}
                 },
-                ActionState::Add(_, _) | ActionState::Update(_, _) => match key.code {
+                ActionState::Add(_, _)
+                | ActionState::Update(_, _)
+                | ActionState::Transfer(_, _, _) => match key.code {
                     KeyCode::Esc => {
                         app.state = ActionState::Normal;
                     }
@@ -160,6 +191,7 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                     KeyCode::Enter => match app.state {
                         ActionState::Add(_, _) => input_actions::add_enter(&mut app),
                         ActionState::Update(_, _) => input_actions::update_enter(&mut app),
+                        ActionState::Transfer(_, _, _) => input_actions::transfer_enter(&mut app),
                         _ => {}
                     },
                     _ => {}
@@ -174,25 +206,36 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
         .split(f.size());
+    // the info block grows with the budgets that are shown below the message
+    let info_height = match app.state {
+        ActionState::Normal => 3 + app.budgets.len() as u16,
+        _ => 3,
+    };
     let month_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(90), Constraint::Percentage(10)].as_ref())
+        .constraints([Constraint::Min(0), Constraint::Length(info_height)].as_ref())
         .split(chunks[1]);
+    let left_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .split(chunks[0]);
     let (left, right) = render_months(app);
-    f.render_stateful_widget(left, chunks[0], &mut app.month_state);
+    f.render_stateful_widget(left, left_chunks[0], &mut app.month_state);
+    let accounts = render_accounts(app);
+    f.render_stateful_widget(accounts, left_chunks[1], &mut app.account_state);
     f.render_stateful_widget(right, month_chunks[0], &mut app.transaction_state);
     let (info, width) = render_info(app);
     f.render_widget(info, month_chunks[1]);
 
     match app.state {
         ActionState::Normal => {}
-        ActionState::Add(_, _) | ActionState::Update(_, _) => {
+        ActionState::Add(_, _) | ActionState::Update(_, _) | ActionState::Transfer(_, _, _) => {
             f.set_cursor(month_chunks[1].x + width + 1, month_chunks[1].y + 1);
         }
     };
 }
 
-fn render_info(app: &mut App) -> (Paragraph, u16) {
+fn render_info(app: &mut App) -> (Paragraph<'_>, u16) {
     let block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
@@ -200,22 +243,42 @@ fn render_info(app: &mut App) -> (Paragraph, u16) {
             ActionState::Normal => "Info",
             ActionState::Add(_, _) => "Add",
             ActionState::Update(_, _) => "Update",
+            ActionState::Transfer(_, _, _) => "Transfer",
         })
         .border_type(BorderType::Plain);
     let (paragraph, width) = match app.state {
         ActionState::Normal => render_normal(app),
         ActionState::Add(a, _) => render_add(app, a),
         ActionState::Update(a, _) => render_update(app, a),
+        ActionState::Transfer(a, _, _) => render_transfer(app, a),
     };
     (paragraph.block(block), width)
 }
 
-fn render_normal(app: &mut App) -> (Paragraph, u16) {
-    let paragraph = Paragraph::new(app.input.clone()).style(Style::default());
+fn render_normal(app: &mut App) -> (Paragraph<'_>, u16) {
+    let mut lines = vec![Spans::from(app.input.clone())];
+    for budget in &app.budgets {
+        let style = if budget.is_over_budget() {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Spans::from(Span::styled(
+            format!(
+                "{}: budget {}, spent {}, remaining {}",
+                budget.category,
+                locale::format_amount(budget.budget),
+                locale::format_amount(budget.spent),
+                locale::format_amount(budget.remaining())
+            ),
+            style,
+        )));
+    }
+    let paragraph = Paragraph::new(lines).style(Style::default());
     (paragraph, 0)
 }
 
-fn render_add(app: &mut App, add_state: AddState) -> (Paragraph, u16) {
+fn render_add(app: &mut App, add_state: AddState) -> (Paragraph<'_>, u16) {
     let text = format!("{}: {}", add_state, app.input);
     (
         Paragraph::new(text.clone()).style(Style::default()),
@@ -223,7 +286,7 @@ fn render_add(app: &mut App, add_state: AddState) -> (Paragraph, u16) {
     )
 }
 
-fn render_update(app: &mut App, update_state: UpdateState) -> (Paragraph, u16) {
+fn render_update(app: &mut App, update_state: UpdateState) -> (Paragraph<'_>, u16) {
     let text = format!("{}: {}", update_state, app.input);
     (
         Paragraph::new(text.clone()).style(Style::default()),
@@ -231,6 +294,54 @@ fn render_update(app: &mut App, update_state: UpdateState) -> (Paragraph, u16) {
     )
 }
 
+fn render_transfer(app: &mut App, transfer_state: TransferState) -> (Paragraph<'_>, u16) {
+    let text = format!("{}: {}", transfer_state, app.input);
+    (
+        Paragraph::new(text.clone()).style(Style::default()),
+        text.width() as u16,
+    )
+}
+
+// Description with the original amount for entries in another currency
+fn get_description(transaction: &Transaction) -> String {
+    if transaction.currency.is_empty() {
+        transaction.description.clone()
+    } else {
+        format!(
+            "{} ({})",
+            transaction.description,
+            Locale::current().format_currency(transaction.amount, &transaction.currency)
+        )
+    }
+}
+
+fn render_accounts<'a>(app: &mut App) -> List<'a> {
+    let accounts = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title("Accounts (Tab)")
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = app
+        .accounts
+        .iter()
+        .map(|account| {
+            let text = match app.balances.get(account) {
+                Some(balance) => format!("{} {}", account, locale::format_amount(*balance)),
+                None => account.clone(),
+            };
+            ListItem::new(Spans::from(vec![Span::styled(text, Style::default())]))
+        })
+        .collect();
+
+    List::new(items).block(accounts).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
 fn render_months<'a>(app: &mut App) -> (List<'a>, Table<'a>) {
     let months = Block::default()
         .borders(Borders::ALL)
@@ -258,15 +369,53 @@ fn render_months<'a>(app: &mut App) -> (List<'a>, Table<'a>) {
 
     let mut rows: Vec<Row> = Vec::new();
 
-    for transaction in &app.transactions {
+    let (balances, occurrence_balances) = app.running_balances();
+
+    for (index, balance) in app.visible_transactions().into_iter().zip(balances) {
+        let transaction = &app.transactions[index];
         let row = Row::new(vec![
-            Cell::from(Span::raw(transaction.date.to_string())),
-            Cell::from(Span::raw(transaction.amount.to_string())),
-            Cell::from(Span::raw(transaction.description.clone())),
+            Cell::from(Span::raw(transaction::format_date(transaction.date))),
+            Cell::from(Span::raw(locale::format_amount(
+                app.amount_in_base(transaction),
+            ))),
+            Cell::from(Span::raw(locale::format_amount(balance))),
+            Cell::from(Span::raw(get_description(transaction))),
+            Cell::from(Span::raw(transaction.category.clone())),
+            Cell::from(Span::raw(set_serializer::set_to_string(&transaction.tags))),
+            Cell::from(Span::raw(set_serializer::set_to_string(
+                &transaction.switches,
+            ))),
         ]);
         rows.push(row)
     }
 
+    // repeated entries are shown below the stored ones and cannot be selected
+    for (occurrence, balance) in app
+        .visible_occurrences()
+        .into_iter()
+        .zip(occurrence_balances)
+    {
+        let row = Row::new(vec![
+            Cell::from(Span::raw(transaction::format_date(occurrence.date))),
+            Cell::from(Span::raw(locale::format_amount(
+                app.amount_in_base(occurrence),
+            ))),
+            Cell::from(Span::raw(locale::format_amount(balance))),
+            Cell::from(Span::raw(format!(
+                "{} ({})",
+                get_description(occurrence),
+                occurrence.repeat.interval
+            ))),
+            Cell::from(Span::raw(occurrence.category.clone())),
+            Cell::from(Span::raw(set_serializer::set_to_string(&occurrence.tags))),
+            Cell::from(Span::raw(set_serializer::set_to_string(
+                &occurrence.switches,
+            ))),
+        ])
+        .style(Style::default().fg(Color::DarkGray));
+        rows.push(row)
+    }
+
     let month_detail = Table::new(rows)
         .header(Row::new(vec![
             Cell::from(Span::styled(
@@ -277,10 +426,26 @@ fn render_months<'a>(app: &mut App) -> (List<'a>, Table<'a>) {
                 "Amount",
                 Style::default().add_modifier(Modifier::BOLD),
             )),
+            Cell::from(Span::styled(
+                "Balance",
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
             Cell::from(Span::styled(
                 "Description",
                 Style::default().add_modifier(Modifier::BOLD),
             )),
+            Cell::from(Span::styled(
+                "Category",
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Cell::from(Span::styled(
+                "Tags",
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Cell::from(Span::styled(
+                "Switches",
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
         ]))
         .block(
             Block::default()
@@ -290,9 +455,13 @@ fn render_months<'a>(app: &mut App) -> (List<'a>, Table<'a>) {
                 .border_type(BorderType::Plain),
         )
         .widths(&[
-            Constraint::Percentage(20),
-            Constraint::Percentage(10),
-            Constraint::Percentage(70),
+            Constraint::Percentage(12),
+            Constraint::Percentage(9),
+            Constraint::Percentage(9),
+            Constraint::Percentage(28),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
         ])
         .highlight_style(
             Style::default()